@@ -0,0 +1,25 @@
+#![allow(non_snake_case)]
+
+use std::path::Path;
+
+
+// Embeds every level file of the levels directory, so that shipping a new sequence takes only its file.
+fn main()
+{
+    println!("cargo:rerun-if-changed=levels");
+    let levelDirectory = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("levels");
+    let mut paths = std::fs::read_dir(&levelDirectory).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut table = format!("const BUILT_IN_LEVEL_FILES: [(&str, &str); {}] = [\n", paths.len());
+    for path in &paths {
+        let fileName = path.file_name().unwrap().to_str().unwrap();
+        table.push_str(&format!("    ({:?}, include_str!({:?})),\n", fileName, path.to_str().unwrap()));
+    }
+    table.push_str("];\n");
+    let outputPath = Path::new(&std::env::var("OUT_DIR").unwrap()).join("built_in_levels.rs");
+    std::fs::write(outputPath, table).unwrap();
+}
@@ -0,0 +1,448 @@
+use crate::cost_model::{Cost, CostModel, MAX_WEIGHT};
+use crate::graph_utils::formatStateSpaceDotGraph;
+use crate::level_file::loadLevelFile;
+use crate::level_generator::{generateLevel, GeneratorSettings};
+use crate::level_maker::{Level, LevelCatalog, makeLevelFrom};
+use crate::level_solver::{
+    enumerateSolutions, findHint, NoSolution, SearchAlgorithm, SearchEnd, SearchStatistics, Solution, SolutionSelection,
+    solveLevelWithStatistics, solvePatternWithStatistics, SolverOptions};
+use crate::rule_set::{DoublerRule, MutationRule, RuleSet, SwapRule};
+use crate::solution_verifier::{loadActionFile, VerificationOutcome, verifySolution};
+use crate::state_space::{exploreStateSpace, StateKind, StateSpace};
+use crate::strand::{MAX_NODE_COUNT, SurfaceMatching};
+use crate::strand_notation::{formatStrand, parseStrand};
+use crate::strand_pattern::parseStrandPattern;
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+
+const USAGE: &str = "Usage:
+    splice-solver                                  Start the graphical interface.
+    splice-solver catalog                          List the sequences of the level catalog and their levels.
+    splice-solver solve <start> <target> <splices> Solve a level given by strands in bracket notation.
+    splice-solver solve-file <path>                Solve every level defined in a level file.
+    splice-solver solve-pattern <start> <pattern> <splices>
+                                                   Reach any strand matching a pattern, where (?) is a cell
+                                                   of any kind and * any subtree, with dijkstra.
+    splice-solver hint <strand> <target> <splices> <used-splices>
+                                                   Suggest the next action for a strand reached partway through a level.
+    splice-solver verify <start> <target> <splices> <actions>
+                                                   Check the actions listed in a file against a level.
+    splice-solver explore <start> <target> <splices> <dot|list>
+                                                   Print every strand reachable within the splices as a graph.
+    splice-solver generate <nodes> <doublers,extenders,erasers> <min-splices> [<seed>]
+                                                   Print a random level needing at least min-splices splices.
+
+Solver options:
+    --algorithm <astar|dijkstra|idastar|bidirectional>
+                                                   Search algorithm to use, astar by default.
+    --minimize <splices|steps|mutations|S,M>       What the solution minimizes, steps by default.
+                                                   S,M weighs each splice by S and each mutation by M, both up to 65536.
+    --optimal-solutions <count>                    Print up to count distinct optimal solutions.
+    --best-solutions <count>                       Print the count best distinct solutions.
+    --statistics                                   Print search statistics after each solution.
+    --time-limit <seconds>                         Stop searching for a level after the given time.
+    --max-expanded-states <count>                  Stop searching for a level after expanding count states.
+    --max-nodes <count>                            Skip mutations which would grow a strand beyond count nodes.
+    --threads <count>                              Number of threads searching for a single solution, 1 by default.
+    --table-size <count>                           Number of states remembered by idastar, 1048576 by default.
+    --dead-ends <prune|keep>                       Whether strands which cannot reach the target are left out,
+                                                   prune by default.
+    --rules <default|game>                         Game mechanics to follow, default by default.
+                                                   The options below override parts of the rules given before them.
+    --doublers <alone|block-mutation>              Whether splices may give a doubler a sibling which blocks mutation.
+    --swaps <splice|free>                          Whether swapping children uses up a splice.
+    --mutation <shallowest|all>                    Whether only the shallowest special cells mutate or all of them.
+    --matching <ordered|unordered>                 Whether the order of children matters when matching the target,
+                                                   ordered by default.
+
+Environment:
+    SPLICE_SOLVER_LEVELS=<directory>               Read the level catalog from the .txt level files in the directory
+                                                   instead of the built-in ones.";
+
+pub(crate) fn runCli(arguments: &[String]) -> Result<()>
+{
+    let (positionals, options) = parseArguments(arguments)?;
+    let options = &options;
+    match positionals[..] {
+        ["catalog"] => printCatalog(),
+        ["solve", start, target, maxSplices] => solveStrands(start, target, maxSplices, options),
+        ["solve-file", path] => solveLevelFile(Path::new(path), options),
+        ["solve-pattern", start, pattern, maxSplices] => solvePattern(start, pattern, maxSplices, options),
+        ["hint", strand, target, maxSplices, usedSplices] => printHint(strand, target, maxSplices, usedSplices, options),
+        ["verify", start, target, maxSplices, actionsPath] => {
+            verifyActions(start, target, maxSplices, Path::new(actionsPath), options)
+        },
+        ["explore", start, target, maxSplices, format] => exploreLevel(start, target, maxSplices, format, options),
+        ["generate", nodeCount, specialCells, minSplices] => {
+            printGeneratedLevel(nodeCount, specialCells, minSplices, None, options)
+        },
+        ["generate", nodeCount, specialCells, minSplices, seed] => {
+            printGeneratedLevel(nodeCount, specialCells, minSplices, Some(seed), options)
+        },
+        _ => bail!("Invalid arguments.\n{}", USAGE)
+    }
+}
+
+#[derive(Default)]
+struct CliOptions
+{
+    solver: SolverOptions,
+    selectionOpt: Option<SolutionSelection>,
+    printStatistics: bool
+}
+
+fn parseArguments(arguments: &[String]) -> Result<(Vec<&str>, CliOptions)>
+{
+    let mut positionals = vec![];
+    let mut options = CliOptions::default();
+    let mut argumentIter = arguments.iter();
+    while let Some(argument) = argumentIter.next() {
+        if !argument.starts_with("--") {
+            positionals.push(argument.as_str());
+            continue;
+        }
+        if argument == "--statistics" {
+            options.printStatistics = true;
+            continue;
+        }
+        let value = argumentIter.next().ok_or_else(|| anyhow!("Option {} requires a value.\n{}", argument, USAGE))?;
+        match argument.as_str() {
+            "--algorithm" => options.solver.algorithm = parseAlgorithm(value)?,
+            "--minimize" => options.solver.costModel = parseCostModel(value)?,
+            "--optimal-solutions" => options.selectionOpt = Some(SolutionSelection::AllOptimal{maxCount: parseCount(value)?}),
+            "--best-solutions" => options.selectionOpt = Some(SolutionSelection::Best{count: parseCount(value)?}),
+            "--time-limit" => options.solver.limits.timeLimitOpt = Some(parseDuration(value)?),
+            "--max-expanded-states" => options.solver.limits.maxExpandedStatesOpt = Some(parseCount(value)?),
+            "--max-nodes" => options.solver.limits.maxNodeCount = parseMaxNodeCount(value)?,
+            "--threads" => options.solver.threadCount = parseCount(value)?,
+            "--table-size" => options.solver.transpositionTableCapacity = parseCount(value)?,
+            "--dead-ends" => options.solver.pruneDeadEnds = parsePruneDeadEnds(value)?,
+            "--rules" => options.solver.rules = parseRuleSet(value)?,
+            "--doublers" => options.solver.rules.doublerRule = parseDoublerRule(value)?,
+            "--swaps" => options.solver.rules.swapRule = parseSwapRule(value)?,
+            "--mutation" => options.solver.rules.mutationRule = parseMutationRule(value)?,
+            "--matching" => options.solver.matching = parseSurfaceMatching(value)?,
+            _ => bail!("Unknown option: {}\n{}", argument, USAGE)
+        }
+    }
+    if options.printStatistics && options.selectionOpt.is_some() {
+        bail!("Search statistics are not available when enumerating solutions.");
+    }
+    Ok((positionals, options))
+}
+
+fn parseCount(text: &str) -> Result<usize>
+{
+    match text.parse() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => bail!("Invalid count: {}", text)
+    }
+}
+
+fn parseMaxNodeCount(text: &str) -> Result<usize>
+{
+    match parseCount(text)? {
+        count if count <= MAX_NODE_COUNT => Ok(count),
+        _ => bail!("Node count cannot exceed {}: {}", MAX_NODE_COUNT, text)
+    }
+}
+
+fn parseDuration(text: &str) -> Result<Duration>
+{
+    match text.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 => Duration::try_from_secs_f64(seconds).map_err(|_| anyhow!("Invalid duration: {}", text)),
+        _ => bail!("Invalid duration: {}", text)
+    }
+}
+
+fn parseAlgorithm(text: &str) -> Result<SearchAlgorithm>
+{
+    match text {
+        "astar" => Ok(SearchAlgorithm::AStar),
+        "dijkstra" => Ok(SearchAlgorithm::Dijkstra),
+        "idastar" => Ok(SearchAlgorithm::IdaStar),
+        "bidirectional" => Ok(SearchAlgorithm::Bidirectional),
+        _ => bail!("Unknown search algorithm: {}", text)
+    }
+}
+
+fn parseRuleSet(text: &str) -> Result<RuleSet>
+{
+    match text {
+        "default" => Ok(RuleSet::default()),
+        "game" => Ok(RuleSet::GAME),
+        _ => bail!("Unknown rule set: {}", text)
+    }
+}
+
+fn parseDoublerRule(text: &str) -> Result<DoublerRule>
+{
+    match text {
+        "alone" => Ok(DoublerRule::Alone),
+        "block-mutation" => Ok(DoublerRule::BlockMutation),
+        _ => bail!("Unknown doubler rule: {}", text)
+    }
+}
+
+fn parseSwapRule(text: &str) -> Result<SwapRule>
+{
+    match text {
+        "splice" => Ok(SwapRule::Splice),
+        "free" => Ok(SwapRule::Free),
+        _ => bail!("Unknown swap rule: {}", text)
+    }
+}
+
+fn parseMutationRule(text: &str) -> Result<MutationRule>
+{
+    match text {
+        "shallowest" => Ok(MutationRule::Shallowest),
+        "all" => Ok(MutationRule::AllDeepestFirst),
+        _ => bail!("Unknown mutation rule: {}", text)
+    }
+}
+
+fn parsePruneDeadEnds(text: &str) -> Result<bool>
+{
+    match text {
+        "prune" => Ok(true),
+        "keep" => Ok(false),
+        _ => bail!("Unknown dead end handling: {}", text)
+    }
+}
+
+fn parseSurfaceMatching(text: &str) -> Result<SurfaceMatching>
+{
+    match text {
+        "ordered" => Ok(SurfaceMatching::Ordered),
+        "unordered" => Ok(SurfaceMatching::Unordered),
+        _ => bail!("Unknown surface matching: {}", text)
+    }
+}
+
+fn parseCostModel(text: &str) -> Result<CostModel>
+{
+    match text {
+        "splices" => Ok(CostModel::FewestSplices),
+        "steps" => Ok(CostModel::FewestSteps),
+        "mutations" => Ok(CostModel::FewestMutations),
+        _ => {
+            let (spliceWeight, mutationWeight) = text.split_once(',')
+                .ok_or_else(|| anyhow!("Unknown cost model: {}", text))?;
+            Ok(CostModel::Weighted{
+                spliceWeight: parseWeight(spliceWeight)?,
+                mutationWeight: parseWeight(mutationWeight)?})
+        }
+    }
+}
+
+fn parseWeight(text: &str) -> Result<Cost>
+{
+    match text.parse() {
+        Ok(weight) if weight <= MAX_WEIGHT => Ok(weight),
+        _ => bail!("Invalid weight, expected a number from 0 to {}: {}", MAX_WEIGHT, text)
+    }
+}
+
+fn printCatalog() -> Result<()>
+{
+    for sequence in LevelCatalog::load()?.sequences() {
+        println!("Sequence {}, {} strands:", sequence.number.0, sequence.strandCount());
+        for level in &sequence.levels {
+            println!("    {}. {}, par {}", level.strand.0, level.name, level.par);
+        }
+    }
+    Ok(())
+}
+
+fn solveStrands(start: &str, target: &str, maxSplices: &str, options: &CliOptions) -> Result<()>
+{
+    printSolution(parseLevel(start, target, maxSplices)?, options);
+    Ok(())
+}
+
+fn solvePattern(start: &str, pattern: &str, maxSplices: &str, options: &CliOptions) -> Result<()>
+{
+    let start = parseStrand(start).context("Invalid start strand")?;
+    let pattern = parseStrandPattern(pattern).context("Invalid pattern")?;
+    let maxSplices = maxSplices.parse().map_err(|_| anyhow!("Invalid splice count: {}", maxSplices))?;
+    let (result, statistics) = solvePatternWithStatistics(start, &pattern, maxSplices, &options.solver);
+    match result {
+        Ok(solution) => printSolutions(&[solution]),
+        Err(noSolution) => printNoSolution(&noSolution)
+    }
+    if options.printStatistics {
+        printStatistics(&statistics);
+    }
+    Ok(())
+}
+
+fn parseLevel(start: &str, target: &str, maxSplices: &str) -> Result<Level>
+{
+    Ok(Level{
+        start: parseStrand(start).context("Invalid start strand")?,
+        target: parseStrand(target).context("Invalid target strand")?,
+        maxSplices: maxSplices.parse().map_err(|_| anyhow!("Invalid splice count: {}", maxSplices))?,
+        optimalSplices: None})
+}
+
+fn printHint(strand: &str, target: &str, maxSplices: &str, usedSplices: &str, options: &CliOptions) -> Result<()>
+{
+    let level = parseLevel(strand, target, maxSplices)?;
+    let usedSplices = usedSplices.parse().map_err(|_| anyhow!("Invalid splice count: {}", usedSplices))?;
+    if usedSplices > level.maxSplices {
+        bail!("Used splices cannot exceed the allowance of {}: {}", level.maxSplices, usedSplices);
+    }
+    match findHint(level.start, level.target, level.maxSplices, usedSplices, &options.solver) {
+        Ok(hint) => match hint.nextStepOpt {
+            Some(nextStep) => {
+                println!("    Next: {}: {}", nextStep.lastAction.as_ref().unwrap(), formatStrand(&nextStep.strand));
+                println!("    {} steps remain with {} splices and {} mutations.",
+                         hint.remainingSteps, hint.remainingSplices, hint.remainingMutations);
+            },
+            None => println!("    The strand already matches the target.")
+        },
+        Err(noSolution) => printNoSolution(&noSolution)
+    }
+    Ok(())
+}
+
+fn verifyActions(start: &str, target: &str, maxSplices: &str, actionsPath: &Path, options: &CliOptions) -> Result<()>
+{
+    let level = parseLevel(start, target, maxSplices)?;
+    let actions = loadActionFile(actionsPath)?;
+    let verification = verifySolution(&level, &actions, &options.solver.rules, options.solver.matching);
+    match verification.outcome {
+        VerificationOutcome::TargetReached => {
+            println!("The target is reached with {} splices and {} mutations.", verification.spliceCount, verification.mutationCount);
+            Ok(())
+        },
+        VerificationOutcome::TargetNotReached => {
+            bail!("The actions end with {} instead of the target.", formatStrand(&verification.strand))
+        },
+        VerificationOutcome::IllegalStep{stepNumber, violation} => {
+            bail!("Step {} is illegal, {}: {}", stepNumber, violation, actions[stepNumber - 1])
+        }
+    }
+}
+
+fn exploreLevel(start: &str, target: &str, maxSplices: &str, format: &str, options: &CliOptions) -> Result<()>
+{
+    let level = parseLevel(start, target, maxSplices)?;
+    let formatStateSpace = match format {
+        "dot" => formatStateSpaceDotGraph,
+        "list" => StateSpace::formatList,
+        _ => bail!("Unknown graph format: {}", format)
+    };
+    let stateSpace = exploreStateSpace(&level, &options.solver);
+    println!("{}", formatStateSpace(&stateSpace));
+    let end = match stateSpace.endOpt {
+        Some(SearchEnd::LimitReached(limit)) => format!("cut short by the {}", limit),
+        Some(SearchEnd::Cancelled) => "cancelled".into(),
+        Some(SearchEnd::Exhausted) | None => "complete".into()
+    };
+    eprintln!("{} states, {} edges, {} goals, {} dead ends, {}.", stateSpace.states.len(), stateSpace.edges.len(),
+              stateSpace.countStates(StateKind::Goal), stateSpace.countStates(StateKind::DeadEnd), end);
+    Ok(())
+}
+
+fn printGeneratedLevel(
+    nodeCount: &str, specialCells: &str, minSplices: &str, seedOpt: Option<&str>, options: &CliOptions) -> Result<()>
+{
+    let specialCellCounts = specialCells.split(',')
+        .map(|count| count.parse().map_err(|_| anyhow!("Invalid special cell count: {}", count)))
+        .collect::<Result<Vec<usize>>>()?;
+    let [doublerCount, extenderCount, eraserCount] = specialCellCounts[..] else {
+        bail!("Expected counts of doublers, extenders and erasers separated by commas: {}", specialCells);
+    };
+    let settings = GeneratorSettings{
+        nodeCount: nodeCount.parse().map_err(|_| anyhow!("Invalid node count: {}", nodeCount))?,
+        doublerCount,
+        extenderCount,
+        eraserCount,
+        minOptimalSplices: minSplices.parse().map_err(|_| anyhow!("Invalid splice count: {}", minSplices))?};
+    let seed = match seedOpt {
+        Some(seed) => seed.parse().map_err(|_| anyhow!("Invalid seed: {}", seed))?,
+        None => SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+    };
+    let level = generateLevel(&settings, seed, &options.solver)?;
+    println!("# Generated with seed {}", seed);
+    println!("sequence 1\n\nstrand 1");
+    println!("start  {}", formatStrand(&level.start));
+    println!("target {}", formatStrand(&level.target));
+    println!("maxSplices {}", level.maxSplices);
+    if let Some(optimalSplices) = level.optimalSplices {
+        println!("optimalSplices {}", optimalSplices);
+    }
+    Ok(())
+}
+
+fn solveLevelFile(path: &Path, options: &CliOptions) -> Result<()>
+{
+    for levelDefinition in loadLevelFile(path)? {
+        let (sequence, strand) = (levelDefinition.sequence.0, levelDefinition.strand.0);
+        println!("Sequence {}, strand {}:", sequence, strand);
+        let level = makeLevelFrom(&levelDefinition.info)
+            .with_context(|| format!("Level of sequence {}, strand {} is invalid", sequence, strand))?;
+        printSolution(level, options);
+    }
+    Ok(())
+}
+
+fn printSolution(level: Level, options: &CliOptions)
+{
+    let (result, statisticsOpt) = match options.selectionOpt {
+        Some(selection) => (enumerateSolutions(level, &options.solver, selection), None),
+        None => {
+            let (result, statistics) = solveLevelWithStatistics(level, &options.solver);
+            (result.map(|solution| vec![solution]), Some(statistics))
+        }
+    };
+    match result {
+        Ok(solutions) => printSolutions(&solutions),
+        Err(noSolution) => printNoSolution(&noSolution)
+    }
+    if let Some(fallback) = statisticsOpt.as_ref().and_then(|statistics| statistics.bidirectionalFallbackOpt) {
+        println!("    {}", fallback);
+    }
+    if let Some(statistics) = statisticsOpt.filter(|_| options.printStatistics) {
+        printStatistics(&statistics);
+    }
+}
+
+fn printSolutions(solutions: &[Solution])
+{
+    for (index, solution) in solutions.iter().enumerate() {
+        if solutions.len() > 1 {
+            println!("    Solution {}:", index + 1);
+        }
+        println!("    Start: {}", formatStrand(&solution.steps[0].strand));
+        for step in solution.steps.iter().skip(1) {
+            println!("    {}: {}", step.lastAction.as_ref().unwrap(), formatStrand(&step.strand));
+        }
+        println!("    Used {} splices and {} mutations in {} steps, the splice count is {}.",
+                 solution.spliceCount, solution.mutationCount, solution.stepCount(), solution.spliceRating);
+    }
+}
+
+fn printNoSolution(noSolution: &NoSolution)
+{
+    println!("    {}", noSolution);
+    if noSolution.closestSurfaceDistanceOpt.is_some() {
+        println!("    Closest strand: {}", formatStrand(&noSolution.closestStrand));
+    }
+}
+
+fn printStatistics(statistics: &SearchStatistics)
+{
+    println!("    Generated {} states and expanded {} of them in {:.3} s.",
+             statistics.statesGenerated, statistics.statesExpanded, statistics.wallTime.as_secs_f64());
+    println!("    Successors: {} by splicing, {} by mutation, {} already seen.",
+             statistics.spliceSuccessors, statistics.mutationSuccessors, statistics.duplicateHits);
+    println!("    Peak frontier size: {}, largest strand: {} cells.", statistics.peakFrontierSize, statistics.maxStrandSize);
+    println!("    Dead ends pruned: {} by node count, {} by special cells.",
+             statistics.deadEndsByNodeCount, statistics.deadEndsByCellKinds);
+}
@@ -0,0 +1,107 @@
+use to_trait::To;
+
+
+pub(crate) type Cost = u64;
+
+// Costs are compared first by what the model minimizes and then by a tie breaker kept in the lowest bits,
+// so that equally good solutions are ordered deterministically.
+const TIE_BREAKER_RANGE: Cost = 1 << 24;
+// Keeps the cost of a path well within the range of Cost, even with millions of actions at the highest weight.
+pub(crate) const MAX_WEIGHT: Cost = 1 << 16;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum CostModel
+{
+    FewestSplices,
+    #[default]
+    FewestSteps,
+    FewestMutations,
+    // Weights must not exceed MAX_WEIGHT.
+    Weighted{spliceWeight: Cost, mutationWeight: Cost}
+}
+
+impl CostModel
+{
+    pub(crate) fn cost(&self, counts: ActionCounts) -> Cost
+    {
+        let splices = counts.splices.try_to::<Cost>().unwrap();
+        let mutations = counts.mutations.try_to::<Cost>().unwrap();
+        let steps = splices + mutations + counts.freeSwaps.try_to::<Cost>().unwrap();
+        let (primaryCost, tieBreaker) = match *self {
+            CostModel::FewestSplices => (splices, steps),
+            CostModel::FewestSteps => (steps, splices),
+            CostModel::FewestMutations => (mutations, steps),
+            CostModel::Weighted{spliceWeight, mutationWeight} => {
+                debug_assert!(spliceWeight <= MAX_WEIGHT && mutationWeight <= MAX_WEIGHT);
+                (splices * spliceWeight + mutations * mutationWeight, steps)
+            }
+        };
+        debug_assert!(tieBreaker < TIE_BREAKER_RANGE);
+        primaryCost * TIE_BREAKER_RANGE + tieBreaker
+    }
+}
+
+// The part of a cost which the model minimizes, without the tie breaker.
+pub(crate) fn primaryCost(cost: Cost) -> Cost
+{
+    cost / TIE_BREAKER_RANGE
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ActionCounts
+{
+    pub splices: usize,
+    pub mutations: usize,
+    // Swaps which do not use up a splice under the rule set still count as steps.
+    pub freeSwaps: usize
+}
+
+impl ActionCounts
+{
+    pub(crate) const SPLICE: Self = Self{splices: 1, mutations: 0, freeSwaps: 0};
+    pub(crate) const MUTATION: Self = Self{splices: 0, mutations: 1, freeSwaps: 0};
+    pub(crate) const FREE_SWAP: Self = Self{splices: 0, mutations: 0, freeSwaps: 1};
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const FEW_SPLICES: ActionCounts = ActionCounts{splices: 1, mutations: 3, freeSwaps: 0};
+    const FEW_STEPS: ActionCounts = ActionCounts{splices: 2, mutations: 0, freeSwaps: 0};
+
+    #[test]
+    fn modelsOrderCostsByWhatTheyMinimize()
+    {
+        assert!(CostModel::FewestSplices.cost(FEW_SPLICES) < CostModel::FewestSplices.cost(FEW_STEPS));
+        assert!(CostModel::FewestSteps.cost(FEW_STEPS) < CostModel::FewestSteps.cost(FEW_SPLICES));
+        assert!(CostModel::FewestMutations.cost(FEW_STEPS) < CostModel::FewestMutations.cost(FEW_SPLICES));
+        let weighted = CostModel::Weighted{spliceWeight: 10, mutationWeight: 1};
+        assert!(weighted.cost(FEW_SPLICES) < weighted.cost(FEW_STEPS));
+    }
+
+    #[test]
+    fn tieBreakerOrdersEquallyGoodCosts()
+    {
+        let withFreeSwap = ActionCounts{splices: 1, mutations: 0, freeSwaps: 1};
+        let withSplice = ActionCounts{splices: 2, mutations: 0, freeSwaps: 0};
+        // Both take 2 steps, so the one using fewer splices is cheaper.
+        assert!(CostModel::FewestSteps.cost(withFreeSwap) < CostModel::FewestSteps.cost(withSplice));
+
+        // Both weigh 4, so the one taking fewer steps is cheaper.
+        let weighted = CostModel::Weighted{spliceWeight: 2, mutationWeight: 1};
+        let mutations = ActionCounts{splices: 0, mutations: 4, freeSwaps: 0};
+        assert!(weighted.cost(withSplice) < weighted.cost(mutations));
+        assert_eq!(primaryCost(weighted.cost(withSplice)), primaryCost(weighted.cost(mutations)));
+        assert_eq!(primaryCost(weighted.cost(mutations)), 4);
+    }
+
+    #[test]
+    fn heaviestPathsStayWithinRange()
+    {
+        let counts = ActionCounts{splices: 1_000_000, mutations: 1_000_000, freeSwaps: 0};
+        let weighted = CostModel::Weighted{spliceWeight: MAX_WEIGHT, mutationWeight: MAX_WEIGHT};
+        assert_eq!(primaryCost(weighted.cost(counts)), 2_000_000 * MAX_WEIGHT);
+    }
+}
@@ -0,0 +1,148 @@
+//! Recognizes strands which can never reach the target, whatever actions follow.
+//!
+//! Splices keep the cells of a strand, so only mutations change what the strand is made of: a doubler adds a copy of
+//! its subtree, an extender adds a cell, an eraser removes its subtree and every mutated cell becomes a normal one.
+//! A special cell is therefore either one of the strand or a copy a doubler made of one. Without doublers the strand
+//! can grow by at most one cell per extender it does not keep for the target, and without erasers able to mutate it
+//! cannot shrink at all.
+
+use crate::strand::{CellKind, Strand};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+const CELL_KIND_COUNT: usize = 4;
+
+pub(crate) struct DeadEndDetector
+{
+    target: CellCounts,
+    // When turned off, no strand is a dead end.
+    isEnabled: bool,
+    // Counted from every thread expanding states, so they are atomic.
+    deadEndsByNodeCount: AtomicUsize,
+    deadEndsByCellKinds: AtomicUsize
+}
+
+impl DeadEndDetector
+{
+    pub(crate) fn new(target: &Strand, isEnabled: bool) -> Self
+    {
+        Self{
+            target: CellCounts::new(target),
+            isEnabled,
+            deadEndsByNodeCount: AtomicUsize::new(0),
+            deadEndsByCellKinds: AtomicUsize::new(0)}
+    }
+
+    pub(crate) fn isDeadEnd(&self, strand: &Strand) -> bool
+    {
+        if !self.isEnabled {
+            return false;
+        }
+        let counts = CellCounts::new(strand);
+        if !self.hasReachableCellKinds(&counts) {
+            self.deadEndsByCellKinds.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+        if !self.hasReachableNodeCount(&counts) {
+            self.deadEndsByNodeCount.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    pub(crate) fn deadEndsByNodeCount(&self) -> usize
+    {
+        self.deadEndsByNodeCount.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn deadEndsByCellKinds(&self) -> usize
+    {
+        self.deadEndsByCellKinds.load(Ordering::Relaxed)
+    }
+
+    fn hasReachableCellKinds(&self, counts: &CellCounts) -> bool
+    {
+        [CellKind::Doubler, CellKind::Extender, CellKind::Eraser].into_iter().all(|cellKind| {
+            let (count, targetCount) = (counts.get(cellKind), self.target.get(cellKind));
+            match counts.get(CellKind::Doubler) {
+                0 => count >= targetCount,
+                _ => count > 0 || targetCount == 0
+            }
+        })
+    }
+
+    // Only called when the cell kinds are reachable, so the strand has at least the special cells of the target
+    // when it has no doublers.
+    fn hasReachableNodeCount(&self, counts: &CellCounts) -> bool
+    {
+        let (doublerCount, eraserCount) = (counts.get(CellKind::Doubler), counts.get(CellKind::Eraser));
+        let canShrink = match doublerCount {
+            0 => eraserCount > self.target.get(CellKind::Eraser),
+            _ => eraserCount > 0
+        };
+        if !canShrink && counts.nodeCount > self.target.nodeCount {
+            return false;
+        }
+        let extendingCount = counts.get(CellKind::Extender).saturating_sub(self.target.get(CellKind::Extender));
+        doublerCount > 0 || counts.nodeCount + extendingCount >= self.target.nodeCount
+    }
+}
+
+struct CellCounts
+{
+    nodeCount: usize,
+    cellKindCounts: [usize; CELL_KIND_COUNT]
+}
+
+impl CellCounts
+{
+    fn new(strand: &Strand) -> Self
+    {
+        let mut newSelf = Self{nodeCount: 0, cellKindCounts: [0; CELL_KIND_COUNT]};
+        for nodeId in strand.collectNodeIds() {
+            newSelf.nodeCount += 1;
+            newSelf.cellKindCounts[usize::from(strand.cellKind(nodeId).code())] += 1;
+        }
+        newSelf
+    }
+
+    fn get(&self, cellKind: CellKind) -> usize
+    {
+        self.cellKindCounts[usize::from(cellKind.code())]
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::cost_model::CostModel;
+    use crate::level_maker::LevelCatalog;
+    use crate::level_solver::{solveLevelWithStatistics, SolverOptions};
+
+    #[test]
+    fn pruningDeadEndsKeepsTheCostOfBuiltInLevels()
+    {
+        let prunedOptions = SolverOptions{costModel: CostModel::FewestSplices, ..SolverOptions::default()};
+        let keptOptions = SolverOptions{pruneDeadEnds: false, ..prunedOptions.clone()};
+        let catalog = LevelCatalog::load().unwrap();
+        let mut prunedCount = 0;
+        for sequence in catalog.sequences() {
+            for metadata in &sequence.levels {
+                let solve = |options| {
+                    let level = catalog.makeLevel(sequence.number, metadata.strand).unwrap();
+                    let (result, statistics) = solveLevelWithStatistics(level, options);
+                    let solution = result.unwrap_or_else(|_| panic!("{} has no solution", metadata.name));
+                    let cost = options.costModel.cost(solution.actionCounts());
+                    (cost, statistics.deadEndsByNodeCount + statistics.deadEndsByCellKinds)
+                };
+                let (prunedCost, prunedDeadEnds) = solve(&prunedOptions);
+                let (keptCost, keptDeadEnds) = solve(&keptOptions);
+                assert_eq!(prunedCost, keptCost, "{}", metadata.name);
+                assert_eq!(keptDeadEnds, 0);
+                prunedCount += prunedDeadEnds;
+            }
+        }
+        assert!(prunedCount > 0);
+    }
+}
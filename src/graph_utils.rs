@@ -1,4 +1,6 @@
+use crate::state_space::{StateKind, StateSpace};
 use crate::strand::{CellKind, NodeId, Strand};
+use crate::strand_notation::formatStrand;
 
 
 pub(crate) fn formatDotGraph(strand: &Strand) -> String
@@ -21,3 +23,24 @@ fn formatNode(nodeId: NodeId, strand: &Strand) -> String
         CellKind::Eraser => format!("\"{} (eraser)\"", nodeId)
     }
 }
+
+// Goals are drawn with a double border, dead ends greyed out and actions using no splice as dashed edges.
+pub(crate) fn formatStateSpaceDotGraph(stateSpace: &StateSpace) -> String
+{
+    let mut output = String::new();
+    output.push_str("digraph {\n");
+    for (index, state) in stateSpace.states.iter().enumerate() {
+        let style = match state.kind {
+            StateKind::Goal => ", peripheries=2",
+            StateKind::DeadEnd => ", style=filled, fillcolor=lightgray",
+            StateKind::Open => ""
+        };
+        output.push_str(&format!("    {} [label=\"{}\"{}]\n", index, formatStrand(&state.step.strand), style));
+    }
+    for edge in &stateSpace.edges {
+        let style = if edge.usedSplices == 0 { ", style=dashed" } else { "" };
+        output.push_str(&format!("    {} -> {} [label=\"{}\"{}]\n", edge.fromIndex, edge.toIndex, edge.action, style));
+    }
+    output.push('}');
+    output
+}
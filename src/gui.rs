@@ -1,14 +1,16 @@
 #![allow(clippy::enum_variant_names)]
 
 use crate::graph_utils::formatDotGraph;
-use crate::level_maker::{makeLevel, SequenceNumber, StrandNumber};
-use crate::level_solver::{Action, solveLevel, SolutionStep};
-use crate::strand::{NodeId, Strand};
+use crate::level_maker::{LevelCatalog, SequenceNumber, StrandNumber};
+use crate::level_solver::{
+    Action, enumerateSolutions, findHint, Hint, NoSolution, SearchEnd, Solution, SolutionSelection, SolutionStep, SolverOptions};
+use crate::search_limits::CancellationToken;
+use crate::strand::Strand;
 
 use anyhow::{bail, Context, Result};
 use gtk::gdk_pixbuf::Pixbuf;
 use gtk::glib;
-use gtk::prelude::{BoxExt, GridExt, GtkWindowExt, ToValue, TreeModelExt, TreeViewExt, WidgetExt};
+use gtk::prelude::{BoxExt, ButtonExt, GridExt, GtkWindowExt, ToValue, TreeModelExt, TreeViewExt, WidgetExt};
 use relm4::gtk;
 use std::fs::write;
 use std::process::Command;
@@ -17,6 +19,7 @@ use to_trait::To;
 
 
 const EXPAND_IN_LAYOUT : bool = true;
+const MAX_DISPLAYED_SOLUTIONS: usize = 20;
 const PRESERVE_ASPECT_RATIO: bool = true;
 const SPACING_I32: i32 = 5;
 const SPACING_U32: u32 = 5;
@@ -25,25 +28,33 @@ const ZEROTH_COLUMN_U32: u32 = 0;
 
 pub(crate) fn makeGui()
 {
+    let catalog = match LevelCatalog::load() {
+        Ok(catalog) => catalog,
+        Err(error) => {
+            eprintln!("{:#}", error);
+            std::process::exit(1);
+        }
+    };
     let app = relm4::RelmApp::new("global.splice-solver");
-    app.run::<AppModel>(());
+    app.run::<AppModel>(catalog);
 }
 
-fn makeSolutionVisuals(solutionOpt: Option<Vec<SolutionStep>>) -> Result<Vec<SolutionStepVisual>>
+fn makeSolutionVisuals(solution: &Solution) -> Result<Vec<SolutionStepVisual>>
 {
-    match solutionOpt {
-        Some(solution) => {
-            match solution.len() {
-                0 => bail!("Solution was found, but has no steps."),
-                1 => bail!("Solution was found, but contains only 1 entry instead of at least 2 - start and end.\
-                            As if the starting state was already solved."),
-                _ => makeValidSolutionVisuals(&solution)
-            }
-        },
-        None => bail!("No solution was found.")
+    match solution.steps.len() {
+        0 => bail!("Solution was found, but has no steps."),
+        1 => bail!("Solution was found, but contains only 1 entry instead of at least 2 - start and end.\
+                    As if the starting state was already solved."),
+        _ => makeValidSolutionVisuals(&solution.steps)
     }
 }
 
+fn makeNoSolutionVisuals(noSolution: &NoSolution) -> Result<Vec<SolutionStepVisual>>
+{
+    let pixbuf = makeStrandPixbuf(&noSolution.closestStrand)?;
+    Ok(vec![SolutionStepVisual{description: "Closest strand reached".into(), pixbuf}])
+}
+
 fn makeValidSolutionVisuals(solution: &[SolutionStep]) -> Result<Vec<SolutionStepVisual>>
 {
     let mut output = vec![];
@@ -58,46 +69,11 @@ fn makeValidSolutionVisuals(solution: &[SolutionStep]) -> Result<Vec<SolutionSte
 fn makeSolutionStepDescription(actionOpt: &Option<Action>) -> String
 {
     match actionOpt {
-        Some(action) => {
-            match action {
-                Action::ChangeParent{node, oldParent, newParent} => {
-                    format!("Change parent of node {} from {} to {}", node, oldParent, newParent)
-                },
-                Action::SwapChildren{parent} => {
-                    format!("Swap children of parent node {}", parent)
-                },
-                Action::Mutate{nodes} => {
-                    makeMutateStepDescription(nodes)
-                }
-            }
-        },
+        Some(action) => action.to_string(),
         None => "Start".into()
     }
 }
 
-fn makeMutateStepDescription(nodes: &[NodeId]) -> String
-{
-    match nodes {
-        [] => panic!("Nodes to mutate cannot be empty"),
-        [nodeId] => format!("Mutate node {}", nodeId),
-        [_, ..] => format!("Mutate nodes {}", formatNodesIntoList(nodes))
-    }
-}
-
-fn formatNodesIntoList(nodes: &[NodeId]) -> String
-{
-    let mut output = String::new();
-    for (index, nodeId) in nodes.iter().enumerate() {
-        output.push_str(&format!("{}", nodeId));
-        match nodes.len() - 1 - index {
-            0 => (),
-            1 => output.push_str(" and "),
-            _ => output.push_str(", ")
-        }
-    }
-    output
-}
-
 fn makeStrandPixbuf(strand: &Strand) -> Result<Pixbuf>
 {
     let dotGraph = formatDotGraph(strand);
@@ -115,9 +91,19 @@ fn makeStrandPixbuf(strand: &Strand) -> Result<Pixbuf>
 
 struct AppModel
 {
+    catalog: LevelCatalog,
     sequenceNumber: SequenceNumber,
     strandNumber: StrandNumber,
     maxStrandNumber: StrandNumber,
+    levelDescription: String,
+    // Why the selected level cannot be solved, when it is not valid.
+    levelErrorOpt: Option<String>,
+    solutions: Vec<Solution>,
+    solutionNumber: usize,
+    noSolutionOpt: Option<NoSolution>,
+    cancellationToken: CancellationToken,
+    hintCancellationToken: CancellationToken,
+    hintDescription: String,
     solutionSteps: Vec<SolutionStepVisual>,
     activeStep: usize,
     solutionStore: gtk::ListStore,
@@ -136,21 +122,58 @@ enum Event
     SelectionChanged(gtk::TreeSelection),
     SequenceNumberChanged(i32),
     StrandNumberChanged(i32),
+    SolutionNumberChanged(i32),
+    HintRequested,
+}
+
+#[derive(Debug)]
+enum BackgroundResult
+{
+    LevelSolved(LevelSolved),
+    HintFound(HintFound)
+}
+
+#[derive(Debug)]
+struct LevelSolved
+{
+    sequenceNumber: SequenceNumber,
+    strandNumber: StrandNumber,
+    result: std::result::Result<Vec<Solution>, NoSolution>
+}
+
+#[derive(Debug)]
+struct HintFound
+{
+    sequenceNumber: SequenceNumber,
+    strandNumber: StrandNumber,
+    solutionNumber: usize,
+    activeStep: usize,
+    result: std::result::Result<Hint, NoSolution>
 }
 
 impl AppModel
 {
-    fn new() -> Self
+    fn new(catalog: LevelCatalog) -> Self
     {
-        let mut newSelf = Self{
-            sequenceNumber: SequenceNumber(1),
+        let firstSequence = &catalog.sequences()[0];
+        let sequenceNumber = firstSequence.number;
+        let maxStrandNumber = StrandNumber(firstSequence.strandCount().try_into().unwrap());
+        Self{
+            catalog,
+            sequenceNumber,
             strandNumber: StrandNumber(1),
-            maxStrandNumber: StrandNumber(7),
+            maxStrandNumber,
+            levelDescription: String::new(),
+            levelErrorOpt: None,
+            solutions: vec![],
+            solutionNumber: 1,
+            noSolutionOpt: None,
+            cancellationToken: CancellationToken::default(),
+            hintCancellationToken: CancellationToken::default(),
+            hintDescription: String::new(),
             solutionSteps: vec![],
             activeStep: 0,
-            solutionStore: gtk::ListStore::new(&[glib::Type::STRING])};
-        newSelf.onLevelChanged();
-        newSelf
+            solutionStore: gtk::ListStore::new(&[glib::Type::STRING])}
     }
 
     fn onSelectionChanged(&mut self, selection: &gtk::TreeSelection)
@@ -160,37 +183,156 @@ impl AppModel
             return;
         }
         self.activeStep = toRowIndex(&rows[0]);
+        self.hintDescription.clear();
     }
 
-    fn onSequenceNumberChanged(&mut self, value: i32)
+    fn onSequenceNumberChanged(&mut self, value: i32, sender: &relm4::ComponentSender<Self>)
     {
         self.sequenceNumber = SequenceNumber(value.try_into().unwrap());
         self.strandNumber = StrandNumber(1);
-        self.maxStrandNumber = StrandNumber(match self.sequenceNumber.0 {
-            1..=4 => 7,
-            5 => 2,
-            number => panic!("Unsupported sequence number: {}", number)
-        });
-        self.onLevelChanged();
+        let sequence = self.catalog.sequences().iter().find(|sequence| sequence.number == self.sequenceNumber).unwrap();
+        self.maxStrandNumber = StrandNumber(sequence.strandCount().try_into().unwrap());
+        self.onLevelChanged(sender);
     }
 
-    fn onStrandNumberChanged(&mut self, value: i32)
+    fn onStrandNumberChanged(&mut self, value: i32, sender: &relm4::ComponentSender<Self>)
     {
         let newStrandNumber = StrandNumber(value.try_into().unwrap());
         if self.strandNumber == newStrandNumber {
             return;
         }
         self.strandNumber = newStrandNumber;
-        self.onLevelChanged();
+        self.onLevelChanged(sender);
+    }
+
+    fn onSolutionNumberChanged(&mut self, value: i32)
+    {
+        let newSolutionNumber = value.try_into().unwrap();
+        if self.solutionNumber == newSolutionNumber {
+            return;
+        }
+        self.solutionNumber = newSolutionNumber;
+        self.onSolutionChanged();
     }
 
-    fn onLevelChanged(&mut self)
+    // Solving runs in the background, so a search for a level which is no longer displayed gets cancelled.
+    fn onLevelChanged(&mut self, sender: &relm4::ComponentSender<Self>)
     {
-        let level = makeLevel(self.sequenceNumber, self.strandNumber).unwrap();
-        let solution = solveLevel(level);
-        let solutionVisuals = makeSolutionVisuals(solution).unwrap();
-        self.solutionSteps = solutionVisuals;
+        self.cancellationToken.cancel();
+        self.cancellationToken = CancellationToken::default();
+        self.hintCancellationToken.cancel();
+        let options = SolverOptions{cancellationToken: self.cancellationToken.clone(), ..SolverOptions::default()};
+        self.levelErrorOpt = None;
+        match self.catalog.makeLevel(self.sequenceNumber, self.strandNumber) {
+            Ok(level) => {
+                let (sequenceNumber, strandNumber) = (self.sequenceNumber, self.strandNumber);
+                sender.spawn_oneshot_command(move || {
+                    let selection = SolutionSelection::AllOptimal{maxCount: MAX_DISPLAYED_SOLUTIONS};
+                    let result = enumerateSolutions(level, &options, selection);
+                    BackgroundResult::LevelSolved(LevelSolved{sequenceNumber, strandNumber, result})
+                });
+            },
+            Err(error) => self.levelErrorOpt = Some(format!("{:#}", error))
+        }
+
+        self.solutions = vec![];
+        self.noSolutionOpt = None;
+        self.solutionNumber = 1;
+        self.onSolutionChanged();
+    }
+
+    fn onLevelSolved(&mut self, levelSolved: LevelSolved)
+    {
+        if levelSolved.sequenceNumber != self.sequenceNumber || levelSolved.strandNumber != self.strandNumber {
+            return;
+        }
+        match levelSolved.result {
+            Ok(solutions) => self.solutions = solutions,
+            Err(noSolution) if noSolution.searchEnd == SearchEnd::Cancelled => return,
+            Err(noSolution) => self.noSolutionOpt = Some(noSolution)
+        }
+        self.onSolutionChanged();
+    }
+
+    // Suggests how to continue from the selected step, as if the player got there on their own. A hint is searched
+    // separately from the level, so a new hint cancels only the previous one.
+    fn onHintRequested(&mut self, sender: &relm4::ComponentSender<Self>)
+    {
+        let step = match self.solutions.get(self.solutionNumber - 1) {
+            Some(solution) => solution.steps[self.activeStep].clone(),
+            None => return
+        };
+        self.hintCancellationToken.cancel();
+        self.hintCancellationToken = CancellationToken::default();
+        let options = SolverOptions{cancellationToken: self.hintCancellationToken.clone(), ..SolverOptions::default()};
+        let level = match self.catalog.makeLevel(self.sequenceNumber, self.strandNumber) {
+            Ok(level) => level,
+            Err(error) => {
+                self.hintDescription = format!("Hint: {:#}", error);
+                return;
+            }
+        };
+        let (sequenceNumber, strandNumber, solutionNumber, activeStep) =
+            (self.sequenceNumber, self.strandNumber, self.solutionNumber, self.activeStep);
+        sender.spawn_oneshot_command(move || {
+            let result = findHint(step.strand, level.target, level.maxSplices, step.spliceCount, &options);
+            BackgroundResult::HintFound(HintFound{sequenceNumber, strandNumber, solutionNumber, activeStep, result})
+        });
+        self.hintDescription = "Looking for a hint...".into();
+    }
+
+    fn onHintFound(&mut self, hintFound: HintFound)
+    {
+        if hintFound.sequenceNumber != self.sequenceNumber || hintFound.strandNumber != self.strandNumber
+            || hintFound.solutionNumber != self.solutionNumber || hintFound.activeStep != self.activeStep {
+            return;
+        }
+        self.hintDescription = match hintFound.result {
+            Ok(Hint{nextStepOpt: Some(nextStep), remainingSteps, ..}) => {
+                format!("Hint: {}\n{} steps remain", nextStep.lastAction.unwrap(), remainingSteps)
+            },
+            Ok(Hint{nextStepOpt: None, ..}) => "Hint: the target is reached".into(),
+            Err(noSolution) if noSolution.searchEnd == SearchEnd::Cancelled => return,
+            Err(noSolution) => format!("Hint: {}", noSolution)
+        };
+    }
+
+    fn onSolutionChanged(&mut self)
+    {
+        let title = match self.catalog.findLevelMetadata(self.sequenceNumber, self.strandNumber) {
+            Ok(metadata) => format!("{}, par {}", metadata.name, metadata.par),
+            Err(error) => format!("{:#}", error)
+        };
+        let solutionOpt = self.solutions.get(self.solutionNumber - 1);
+        let solutionVisuals = match (&self.levelErrorOpt, &self.noSolutionOpt, solutionOpt) {
+            (Some(levelError), _, _) => {
+                self.levelDescription = format!("{}\n{}", title, levelError);
+                Ok(vec![])
+            },
+            (None, Some(noSolution), _) => {
+                self.levelDescription = format!("{}\n{}", title, noSolution);
+                makeNoSolutionVisuals(noSolution)
+            },
+            (None, None, Some(solution)) => {
+                self.levelDescription = format!(
+                    "{}\nSolved in {} steps with {} splices, {}\n{} optimal solutions found",
+                    title, solution.stepCount(), solution.spliceCount, solution.spliceRating, self.solutions.len());
+                makeSolutionVisuals(solution)
+            },
+            (None, None, None) => {
+                self.levelDescription = format!("{}\nSolving...", title);
+                Ok(vec![])
+            }
+        };
+        self.solutionSteps = match solutionVisuals {
+            Ok(solutionVisuals) => solutionVisuals,
+            Err(error) => {
+                self.levelDescription.push_str(&format!("\nThe solution cannot be drawn: {:#}", error));
+                vec![]
+            }
+        };
         self.activeStep = 0;
+        self.hintDescription.clear();
         self.solutionStore.clear();
         for step in &self.solutionSteps {
             self.solutionStore.set_value(&self.solutionStore.append(), ZEROTH_COLUMN_U32, &step.description.to_value());
@@ -202,14 +344,18 @@ struct AppWidgets
 {
     paned: gtk::Paned,
     strandSpinButton: gtk::SpinButton,
+    solutionSpinButton: gtk::SpinButton,
+    levelLabel: gtk::Label,
+    hintLabel: gtk::Label,
     listView: gtk::TreeView,
 }
 
-impl relm4::SimpleComponent for AppModel
+impl relm4::Component for AppModel
 {
+    type CommandOutput = BackgroundResult;
     type Input = Event;
     type Output = ();
-    type Init = ();
+    type Init = LevelCatalog;
     type Root = gtk::ApplicationWindow;
     type Widgets = AppWidgets;
 
@@ -221,24 +367,37 @@ impl relm4::SimpleComponent for AppModel
         appWindow
     }
 
-    fn init(_: Self::Init, appWindow: &Self::Root, sender: relm4::ComponentSender<Self>) -> relm4::ComponentParts<Self>
+    fn init(catalog: Self::Init, appWindow: &Self::Root, sender: relm4::ComponentSender<Self>)
+        -> relm4::ComponentParts<Self>
     {
-        let model = AppModel::new();
+        let mut model = AppModel::new(catalog);
+        model.onLevelChanged(&sender);
 
-        let sequenceSpinButton = gtk::SpinButton::with_range(1.0, 5.0, 1.0);
+        let firstSequenceNumber = model.catalog.sequences().first().unwrap().number.0;
+        let lastSequenceNumber = model.catalog.sequences().last().unwrap().number.0;
+        let sequenceSpinButton =
+            gtk::SpinButton::with_range(firstSequenceNumber.into(), lastSequenceNumber.into(), 1.0);
         sequenceSpinButton.set_can_focus(false);
         let sender2 = sender.clone();
         sequenceSpinButton.connect_value_changed(move |spinButton| {
             sender2.input(Event::SequenceNumberChanged(spinButton.value_as_int()));
         });
 
-        let strandSpinButton = gtk::SpinButton::with_range(1.0, 7.0, 1.0);
+        let strandSpinButton = gtk::SpinButton::with_range(1.0, model.maxStrandNumber.0.into(), 1.0);
         strandSpinButton.set_can_focus(false);
         let sender3 = sender.clone();
         strandSpinButton.connect_value_changed(move |spinButton| {
             sender3.input(Event::StrandNumberChanged(spinButton.value_as_int()));
         });
 
+        let solutionCount = model.solutions.len().max(1).try_to::<i32>().unwrap();
+        let solutionSpinButton = gtk::SpinButton::with_range(1.0, solutionCount.into(), 1.0);
+        solutionSpinButton.set_can_focus(false);
+        let sender4 = sender.clone();
+        solutionSpinButton.connect_value_changed(move |spinButton| {
+            sender4.input(Event::SolutionNumberChanged(spinButton.value_as_int()));
+        });
+
         let parametersGrid = gtk::Grid::default();
         parametersGrid.set_row_spacing(SPACING_U32);
         parametersGrid.set_column_spacing(SPACING_U32);
@@ -246,6 +405,17 @@ impl relm4::SimpleComponent for AppModel
         parametersGrid.attach(&sequenceSpinButton, 1, 0, 1, 1);
         parametersGrid.attach(&gtk::Label::new(Some("Strand")), 0, 1, 1, 1);
         parametersGrid.attach(&strandSpinButton, 1, 1, 1, 1);
+        parametersGrid.attach(&gtk::Label::new(Some("Solution")), 0, 2, 1, 1);
+        parametersGrid.attach(&solutionSpinButton, 1, 2, 1, 1);
+        let levelLabel = gtk::Label::new(Some(&model.levelDescription));
+        parametersGrid.attach(&levelLabel, 0, 3, 2, 1);
+        let hintButton = gtk::Button::with_label("Hint");
+        hintButton.set_can_focus(false);
+        let sender5 = sender.clone();
+        hintButton.connect_clicked(move |_| sender5.input(Event::HintRequested));
+        parametersGrid.attach(&hintButton, 0, 4, 2, 1);
+        let hintLabel = gtk::Label::new(Some(&model.hintDescription));
+        parametersGrid.attach(&hintLabel, 0, 5, 2, 1);
 
         let listViewColumn = gtk::TreeViewColumn::default();
         listViewColumn.set_title("Solution steps");
@@ -274,19 +444,35 @@ impl relm4::SimpleComponent for AppModel
 
         appWindow.set_child(Some(&paned));
 
-        let widgets = AppWidgets{paned, strandSpinButton, listView};
+        let widgets = AppWidgets{paned, strandSpinButton, solutionSpinButton, levelLabel, hintLabel, listView};
         relm4::ComponentParts{model, widgets}
     }
 
-    fn update(&mut self, event: Self::Input, _sender: relm4::ComponentSender<Self>)
+    fn update(&mut self, event: Self::Input, sender: relm4::ComponentSender<Self>, _root: &Self::Root)
     {
         match event {
             Event::SelectionChanged(selection) => self.onSelectionChanged(&selection),
-            Event::SequenceNumberChanged(value) => self.onSequenceNumberChanged(value),
-            Event::StrandNumberChanged(value) => self.onStrandNumberChanged(value)
+            Event::SequenceNumberChanged(value) => self.onSequenceNumberChanged(value, &sender),
+            Event::StrandNumberChanged(value) => self.onStrandNumberChanged(value, &sender),
+            Event::SolutionNumberChanged(value) => self.onSolutionNumberChanged(value),
+            Event::HintRequested => self.onHintRequested(&sender)
         };
     }
 
+    fn update_cmd(&mut self, result: Self::CommandOutput, _sender: relm4::ComponentSender<Self>, _root: &Self::Root)
+    {
+        match result {
+            BackgroundResult::LevelSolved(levelSolved) => self.onLevelSolved(levelSolved),
+            BackgroundResult::HintFound(hintFound) => self.onHintFound(hintFound)
+        }
+    }
+
+    fn shutdown(&mut self, _widgets: &mut Self::Widgets, _output: relm4::Sender<Self::Output>)
+    {
+        self.cancellationToken.cancel();
+        self.hintCancellationToken.cancel();
+    }
+
     fn update_view(&self, widgets: &mut Self::Widgets, _sender: relm4::ComponentSender<Self>)
     {
         if widgets.strandSpinButton.value_as_int() != self.strandNumber.0.to::<i32>() {
@@ -297,10 +483,29 @@ impl relm4::SimpleComponent for AppModel
             widgets.strandSpinButton.set_range(1.0, self.maxStrandNumber.0.into());
         }
 
-        if widgets.listView.selection().count_selected_rows() == 0 {
-            widgets.listView.selection().select_iter(&widgets.listView.model().unwrap().iter_first().unwrap());
+        let solutionNumber = self.solutionNumber.try_to::<i32>().unwrap();
+        if widgets.solutionSpinButton.value_as_int() != solutionNumber {
+            widgets.solutionSpinButton.set_value(solutionNumber.into());
+        }
+
+        let solutionCount = self.solutions.len().max(1).try_to::<i32>().unwrap();
+        if widgets.solutionSpinButton.range().1 != solutionCount.into() {
+            widgets.solutionSpinButton.set_range(1.0, solutionCount.into());
+        }
+
+        widgets.levelLabel.set_text(&self.levelDescription);
+        widgets.hintLabel.set_text(&self.hintDescription);
+
+        // There are no steps to show while the level is being solved.
+        match self.solutionSteps.get(self.activeStep) {
+            Some(step) => {
+                if widgets.listView.selection().count_selected_rows() == 0 {
+                    widgets.listView.selection().select_iter(&widgets.listView.model().unwrap().iter_first().unwrap());
+                }
+                widgets.paned.set_end_child(Some(&gtk::Image::from_pixbuf(Some(&step.pixbuf))));
+            },
+            None => widgets.paned.set_end_child(None::<&gtk::Widget>)
         }
-        widgets.paned.set_end_child(Some(&gtk::Image::from_pixbuf(Some(&self.solutionSteps[self.activeStep].pixbuf))));
     }
 }
 
@@ -0,0 +1,81 @@
+use crate::cost_model::ActionCounts;
+use crate::level_maker::SpliceCount;
+use crate::rule_set::SwapRule;
+use crate::strand::{CellKind, MAX_CHILD_COUNT, Strand, SurfaceMatching};
+
+
+const CHILD_COUNT_CHANGE_PER_SPLICE: usize = 4;
+
+pub(crate) struct StrandHeuristic<'a>
+{
+    target: &'a Strand,
+    targetProfile: StrandProfile,
+    swapRule: SwapRule,
+    matching: SurfaceMatching
+}
+
+impl<'a> StrandHeuristic<'a>
+{
+    pub(crate) fn new(target: &'a Strand, swapRule: SwapRule, matching: SurfaceMatching) -> Self
+    {
+        Self{target, targetProfile: StrandProfile::new(target), swapRule, matching}
+    }
+
+    // Returns a lower bound of the numbers of splices and mutations needed to reach the target,
+    // or None when the target cannot be reached at all.
+    pub(crate) fn estimateRemainingActions(&self, strand: &Strand, remainingSplices: SpliceCount) -> Option<ActionCounts>
+    {
+        let profile = StrandProfile::new(strand);
+        if profile.specialCellCount > 0 {
+            // Mutations can reshape the strand arbitrarily, but at least one is needed to get rid of special cells.
+            return match self.targetProfile.specialCellCount {
+                0 => Some(ActionCounts::MUTATION),
+                _ => Some(ActionCounts::default())
+            };
+        }
+
+        // Only splices are possible from here, so the cells stay the same and only their connections change.
+        if profile.nodeCount != self.targetProfile.nodeCount || self.targetProfile.specialCellCount > 0 {
+            return None;
+        }
+        let childCountDifference = profile.childCountHistogram.iter().zip(&self.targetProfile.childCountHistogram)
+            .map(|(count, targetCount)| count.abs_diff(*targetCount))
+            .sum::<usize>();
+        // A splice removes a child from one cell and adds it to another, which changes at most 4 histogram entries.
+        let mut requiredSplices = childCountDifference.div_ceil(CHILD_COUNT_CHANGE_PER_SPLICE);
+        if requiredSplices == 0 && !strand.matchesSurface(self.target, self.matching) {
+            // The cells might only be in a different order, which a free swap fixes without a splice.
+            if self.swapRule == SwapRule::Free {
+                return Some(ActionCounts::FREE_SWAP);
+            }
+            requiredSplices = 1;
+        }
+        if requiredSplices > remainingSplices.into() {
+            return None;
+        }
+        Some(ActionCounts{splices: requiredSplices, ..ActionCounts::default()})
+    }
+}
+
+struct StrandProfile
+{
+    nodeCount: usize,
+    specialCellCount: usize,
+    childCountHistogram: [usize; MAX_CHILD_COUNT + 1]
+}
+
+impl StrandProfile
+{
+    fn new(strand: &Strand) -> Self
+    {
+        let mut newSelf = Self{nodeCount: 0, specialCellCount: 0, childCountHistogram: [0; MAX_CHILD_COUNT + 1]};
+        for nodeId in strand.collectNodeIds() {
+            newSelf.nodeCount += 1;
+            if strand.cellKind(nodeId) != CellKind::Normal {
+                newSelf.specialCellCount += 1;
+            }
+            newSelf.childCountHistogram[strand.childCount(nodeId)] += 1;
+        }
+        newSelf
+    }
+}
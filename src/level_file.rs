@@ -0,0 +1,320 @@
+//! Text format for level definitions.
+//!
+//! A level file is read line by line. Everything after a `#` is a comment, blank lines are ignored
+//! and the remaining lines consist of whitespace separated tokens:
+//!
+//! ```text
+//! sequence 2
+//!
+//! strand 1
+//! start  nodes 3 edges 0-1 1-2 mutables 1:doubler
+//! target nodes 5 edges 0-1 1-2 0-3 3-4
+//! maxSplices 1
+//! ```
+//!
+//! `sequence <number>` opens a sequence and `strand <number>` opens a level inside it. Both are numbered from 1,
+//! a sequence is opened only once and its strands follow each other without gaps. Every level needs
+//! exactly one `start`, `target` and `maxSplices` line and may be given a `name` spanning the rest of its line.
+//! `maxSplices` is the splice allowance of the game, while an optional `optimalSplices` line records the
+//! smallest number of splices the level is known to be solvable with. A strand is described by its node count, followed by
+//! optional parent-child edges and optional special cells, where a cell kind is `doubler`, `extender` or
+//! `eraser`. Node 0 is the root. Alternatively a strand can be written in the bracket notation described in
+//! `strand_notation`, for example `target (()(()))`.
+
+use crate::level_maker::{LevelInfo, SequenceNumber, SpliceCount, StrandInfo, StrandNumber};
+use crate::strand::{CellKind, Edge, NodeId};
+use crate::strand_notation::parseStrandNotation;
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::Path;
+use std::str::FromStr;
+
+
+pub(crate) fn loadLevelFile(path: &Path) -> Result<Vec<LevelDefinition>>
+{
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read level file {}", path.display()))?;
+    parseLevelFile(&text).with_context(|| format!("Failed to parse level file {}", path.display()))
+}
+
+pub(crate) fn parseLevelFile(text: &str) -> Result<Vec<LevelDefinition>>
+{
+    let mut parser = Parser::default();
+    for (index, line) in text.lines().enumerate() {
+        let lineNumber = index + 1;
+        parser.parseLine(line, lineNumber).with_context(|| format!("Line {}", lineNumber))?;
+    }
+    parser.finishLevel()?;
+    Ok(parser.levels)
+}
+
+pub(crate) struct LevelDefinition
+{
+    pub sequence: SequenceNumber,
+    pub strand: StrandNumber,
+    pub name: Option<String>,
+    pub info: LevelInfo
+}
+
+#[derive(Default)]
+struct Parser
+{
+    sequenceOpt: Option<SequenceNumber>,
+    levelOpt: Option<PartialLevel>,
+    levels: Vec<LevelDefinition>
+}
+
+impl Parser
+{
+    fn parseLine(&mut self, line: &str, lineNumber: usize) -> Result<()>
+    {
+        let content = match line.find('#') {
+            Some(commentStart) => &line[..commentStart],
+            None => line
+        };
+        let mut tokens = content.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => return Ok(())
+        };
+        let arguments = tokens.collect::<Vec<_>>();
+
+        match keyword {
+            "sequence" => {
+                self.finishLevel()?;
+                let sequence = SequenceNumber(parseSingleNumber(keyword, &arguments)?);
+                if sequence.0 == 0 {
+                    bail!("Sequence numbers start at 1, got 0.");
+                }
+                if self.levels.iter().any(|level| level.sequence == sequence) || self.sequenceOpt == Some(sequence) {
+                    bail!("Sequence {} is defined more than once.", sequence.0);
+                }
+                self.sequenceOpt = Some(sequence);
+            },
+            "strand" => {
+                self.finishLevel()?;
+                let sequence = self.sequenceOpt.ok_or_else(|| anyhow!("Strand is defined before any sequence."))?;
+                let strand = StrandNumber(parseSingleNumber(keyword, &arguments)?);
+                let expectedStrand = self.levels.iter().filter(|level| level.sequence == sequence).count() + 1;
+                match usize::from(strand.0) {
+                    0 => bail!("Strand numbers start at 1, got 0."),
+                    number if number < expectedStrand => {
+                        bail!("Strand {} is defined more than once in sequence {}.", strand.0, sequence.0)
+                    },
+                    number if number > expectedStrand => {
+                        bail!("Strand {} should be strand {}, strands must be numbered without gaps.",
+                              strand.0, expectedStrand)
+                    },
+                    _ => ()
+                }
+                self.levelOpt = Some(PartialLevel::new(strand, lineNumber));
+            },
+            "start" => {
+                let level = self.currentLevel(keyword)?;
+                setOnce(&mut level.startOpt, parseStrandInfo(&arguments)?, keyword)?;
+            },
+            "target" => {
+                let level = self.currentLevel(keyword)?;
+                setOnce(&mut level.targetOpt, parseStrandInfo(&arguments)?, keyword)?;
+            },
+            "name" => {
+                if arguments.is_empty() {
+                    bail!("Keyword {} expects a name.", keyword);
+                }
+                setOnce(&mut self.currentLevel(keyword)?.nameOpt, arguments.join(" "), keyword)?;
+            },
+            "maxSplices" => {
+                let maxSplices = parseSingleNumber(keyword, &arguments)?;
+                setOnce(&mut self.currentLevel(keyword)?.maxSplicesOpt, maxSplices, keyword)?;
+            },
+            "optimalSplices" => {
+                let optimalSplices = parseSingleNumber(keyword, &arguments)?;
+                setOnce(&mut self.currentLevel(keyword)?.optimalSplicesOpt, optimalSplices, keyword)?;
+            },
+            _ => bail!("Unknown keyword: {}", keyword)
+        }
+        Ok(())
+    }
+
+    fn currentLevel(&mut self, keyword: &str) -> Result<&mut PartialLevel>
+    {
+        self.levelOpt.as_mut().ok_or_else(|| anyhow!("Keyword {} must follow a strand line.", keyword))
+    }
+
+    fn finishLevel(&mut self) -> Result<()>
+    {
+        let level = match self.levelOpt.take() {
+            Some(level) => level,
+            None => return Ok(())
+        };
+        let lineNumber = level.lineNumber;
+        let missing = |keyword: &str|
+            anyhow!("Strand {} defined at line {} has no {} line.", level.strand.0, lineNumber, keyword);
+        let info = LevelInfo{
+            start: level.startOpt.ok_or_else(|| missing("start"))?,
+            target: level.targetOpt.ok_or_else(|| missing("target"))?,
+            maxSplices: level.maxSplicesOpt.ok_or_else(|| missing("maxSplices"))?,
+            optimalSplices: level.optimalSplicesOpt};
+        self.levels.push(
+            LevelDefinition{sequence: self.sequenceOpt.unwrap(), strand: level.strand, name: level.nameOpt, info});
+        Ok(())
+    }
+}
+
+struct PartialLevel
+{
+    strand: StrandNumber,
+    lineNumber: usize,
+    nameOpt: Option<String>,
+    startOpt: Option<StrandInfo>,
+    targetOpt: Option<StrandInfo>,
+    maxSplicesOpt: Option<SpliceCount>,
+    optimalSplicesOpt: Option<SpliceCount>
+}
+
+impl PartialLevel
+{
+    fn new(strand: StrandNumber, lineNumber: usize) -> Self
+    {
+        Self{strand, lineNumber, nameOpt: None, startOpt: None, targetOpt: None, maxSplicesOpt: None, optimalSplicesOpt: None}
+    }
+}
+
+fn setOnce<T>(slot: &mut Option<T>, value: T, keyword: &str) -> Result<()>
+{
+    if slot.is_some() {
+        bail!("Keyword {} is repeated for the same strand.", keyword);
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn parseSingleNumber<T: FromStr>(keyword: &str, arguments: &[&str]) -> Result<T>
+{
+    match arguments {
+        [argument] => parseNumber(argument),
+        _ => bail!("Keyword {} expects exactly 1 number, got {} arguments.", keyword, arguments.len())
+    }
+}
+
+fn parseNumber<T: FromStr>(text: &str) -> Result<T>
+{
+    text.parse().map_err(|_| anyhow!("Invalid number: {}", text))
+}
+
+fn parseStrandInfo(arguments: &[&str]) -> Result<StrandInfo>
+{
+    if arguments.first().is_some_and(|argument| argument.starts_with('(')) {
+        return parseStrandNotation(&arguments.concat());
+    }
+
+    let mut nodeCountOpt = None;
+    let mut edges = vec![];
+    let mut mutables = vec![];
+    let mut section = "";
+    for argument in arguments {
+        match *argument {
+            "nodes" | "edges" | "mutables" => {
+                section = argument;
+                continue;
+            },
+            _ => ()
+        }
+        match section {
+            "nodes" => setOnce(&mut nodeCountOpt, parseNumber(argument)?, "nodes")?,
+            "edges" => edges.push(parseEdge(argument)?),
+            "mutables" => mutables.push(parseMutable(argument)?),
+            _ => bail!("Expected nodes, edges or mutables, got: {}", argument)
+        }
+    }
+    let nodeCount = nodeCountOpt.ok_or_else(|| anyhow!("Strand has no node count."))?;
+    Ok(StrandInfo{nodeCount, edges, mutables})
+}
+
+fn parseEdge(text: &str) -> Result<Edge>
+{
+    let (parent, child) = text.split_once('-').ok_or_else(|| anyhow!("Invalid edge, expected parent-child: {}", text))?;
+    Ok((parseNumber(parent)?, parseNumber(child)?))
+}
+
+fn parseMutable(text: &str) -> Result<(NodeId, CellKind)>
+{
+    let (nodeId, cellKind) = text.split_once(':').ok_or_else(|| anyhow!("Invalid mutable, expected id:kind: {}", text))?;
+    Ok((parseNumber(nodeId)?, parseCellKind(cellKind)?))
+}
+
+fn parseCellKind(text: &str) -> Result<CellKind>
+{
+    match text {
+        "doubler" => Ok(CellKind::Doubler),
+        "extender" => Ok(CellKind::Extender),
+        "eraser" => Ok(CellKind::Eraser),
+        _ => bail!("Unknown cell kind: {}", text)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn validFileIsParsed()
+    {
+        let levels = parseLevelFile("
+            sequence 2  # Comments are ignored.
+
+            strand 1
+            name First
+            start  nodes 3 edges 0-1 1-2 mutables 1:doubler
+            target nodes 5 edges 0-1 1-2 0-3 3-4
+            maxSplices 1
+
+            strand 2
+            start  ((E))
+            target (()())
+            maxSplices 2
+            optimalSplices 1").unwrap();
+        assert_eq!(levels.len(), 2);
+        assert_eq!((levels[0].sequence, levels[0].strand), (SequenceNumber(2), StrandNumber(1)));
+        assert_eq!(levels[0].name.as_deref(), Some("First"));
+        assert_eq!(levels[0].info.start.edges, vec![(0, 1), (1, 2)]);
+        assert_eq!(levels[0].info.start.mutables, vec![(1, CellKind::Doubler)]);
+        assert_eq!((levels[0].info.target.nodeCount, levels[0].info.maxSplices), (5, 1));
+        assert_eq!(levels[0].info.optimalSplices, None);
+        assert_eq!((levels[1].strand, levels[1].name.as_deref()), (StrandNumber(2), None));
+        assert_eq!(levels[1].info.start.mutables, vec![(1, CellKind::Extender)]);
+        assert_eq!(levels[1].info.optimalSplices, Some(1));
+    }
+
+    #[test]
+    fn unknownKeywordIsReportedWithItsLine()
+    {
+        assert_eq!(parseError("sequence 1\nstrand 1\nsplices 2\n"), "Line 3: Unknown keyword: splices");
+    }
+
+    #[test]
+    fn missingFieldIsReportedWithTheLineOfItsStrand()
+    {
+        assert_eq!(parseError("sequence 1\n\nstrand 1\nstart (())\ntarget (()())\n"),
+                   "Strand 1 defined at line 3 has no maxSplices line.");
+    }
+
+    #[test]
+    fn invalidNumbersAreReportedWithTheirLine()
+    {
+        let level = "start (())\ntarget (()())\nmaxSplices 1\n";
+        assert_eq!(parseError(&format!("sequence 1\nstrand 1\n{}strand 1\n{}", level, level)),
+                   "Line 6: Strand 1 is defined more than once in sequence 1.");
+        assert_eq!(parseError(&format!("sequence 1\nstrand 1\n{}strand 3\n{}", level, level)),
+                   "Line 6: Strand 3 should be strand 2, strands must be numbered without gaps.");
+        assert_eq!(parseError(&format!("sequence 1\nstrand 0\n{}", level)), "Line 2: Strand numbers start at 1, got 0.");
+        assert_eq!(parseError("sequence 0\n"), "Line 1: Sequence numbers start at 1, got 0.");
+        assert_eq!(parseError(&format!("sequence 1\nstrand 1\n{}sequence 1\n", level)),
+                   "Line 6: Sequence 1 is defined more than once.");
+    }
+
+    fn parseError(text: &str) -> String
+    {
+        format!("{:#}", parseLevelFile(text).err().unwrap())
+    }
+}
@@ -0,0 +1,188 @@
+//! Generates random levels which are known to be solvable.
+//!
+//! The start strand is a random tree with the requested special cells. The target is where a random walk of legal
+//! splices and mutations from the start ends, so the level can be solved by repeating the walk. The solver then finds
+//! the fewest splices the target can actually be reached with, and levels easier than requested are thrown away.
+//! The same seed and settings always generate the same level.
+
+use crate::cost_model::CostModel;
+use crate::level_maker::{Level, SpliceCount};
+use crate::level_solver::{Action, makeSuccessors, SolutionStep, solveLevelWithStatistics, SolverOptions};
+use crate::strand::{CellKind, Edge, MAX_CHILD_COUNT, NodeId, Strand};
+
+use anyhow::{bail, Result};
+use itertools::Itertools as _;
+use to_trait::To;
+
+
+const MAX_ATTEMPTS: usize = 1000;
+// The walk may use a splice more than requested, because random splices often undo each other.
+const SPARE_WALK_SPLICES: SpliceCount = 1;
+// A possible mutation is taken once in this many steps, otherwise the walk would hardly ever mutate among all splices.
+const MUTATION_CHANCE: usize = 3;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct GeneratorSettings
+{
+    pub nodeCount: usize,
+    pub doublerCount: usize,
+    pub extenderCount: usize,
+    pub eraserCount: usize,
+    pub minOptimalSplices: SpliceCount
+}
+
+pub(crate) fn generateLevel(settings: &GeneratorSettings, seed: u64, options: &SolverOptions) -> Result<Level>
+{
+    let specialCellCount = settings.doublerCount + settings.extenderCount + settings.eraserCount;
+    if settings.nodeCount == 0 || specialCellCount >= settings.nodeCount {
+        bail!("A strand of {} nodes cannot have {} special cells besides its root.",
+              settings.nodeCount, specialCellCount);
+    }
+    let options = SolverOptions{costModel: CostModel::FewestSplices, ..options.clone()};
+    let mut random = Random::new(seed);
+    for _ in 0..MAX_ATTEMPTS {
+        let Some(start) = makeRandomStrand(settings, &mut random) else {
+            continue;
+        };
+        let walkSplices = settings.minOptimalSplices + SPARE_WALK_SPLICES;
+        let target = walkRandomly(start.clone(), walkSplices, &options, &mut random);
+        let level = Level{start: start.clone(), target: target.clone(), maxSplices: walkSplices, optimalSplices: None};
+        let (result, _statistics) = solveLevelWithStatistics(level, &options);
+        match result {
+            Ok(solution) if solution.stepCount() > 0 && solution.spliceCount >= settings.minOptimalSplices => {
+                let optimalSplices = solution.spliceCount;
+                return Ok(Level{start, target, maxSplices: optimalSplices, optimalSplices: Some(optimalSplices)});
+            },
+            _ => continue
+        }
+    }
+    bail!("No level needing at least {} splices was found in {} attempts.", settings.minOptimalSplices, MAX_ATTEMPTS)
+}
+
+// Every node gets a random parent among the earlier nodes which have room for a child. Doublers go only where
+// they are the only child, so the strand might not fit the requested cells, in which case None is returned.
+fn makeRandomStrand(settings: &GeneratorSettings, random: &mut Random) -> Option<Strand>
+{
+    let mut childCounts = vec![0; settings.nodeCount];
+    let mut edges: Vec<Edge> = vec![];
+    for childId in 1..settings.nodeCount {
+        let parentIds = (0..childId).filter(|parentId| childCounts[*parentId] < MAX_CHILD_COUNT).collect_vec();
+        let parentId = parentIds[random.below(parentIds.len())];
+        childCounts[parentId] += 1;
+        edges.push((parentId.try_to::<NodeId>().ok()?, childId.try_to::<NodeId>().ok()?));
+    }
+
+    let mut freeIds = random.shuffle(edges.iter().map(|edge| edge.1).collect_vec());
+    let mut mutables = vec![];
+    for _ in 0..settings.doublerCount {
+        let position = freeIds.iter().position(|nodeId| {
+            edges.iter().find(|edge| edge.1 == *nodeId).is_some_and(|edge| childCounts[edge.0.to::<usize>()] == 1)
+        })?;
+        mutables.push((freeIds.remove(position), CellKind::Doubler));
+    }
+    let otherCellKinds = std::iter::repeat_n(CellKind::Extender, settings.extenderCount)
+        .chain(std::iter::repeat_n(CellKind::Eraser, settings.eraserCount));
+    for cellKind in otherCellKinds {
+        mutables.push((freeIds.pop().unwrap(), cellKind));
+    }
+    Strand::tryNew(settings.nodeCount, &edges, &mutables).ok()
+}
+
+fn walkRandomly(start: Strand, maxSplices: SpliceCount, options: &SolverOptions, random: &mut Random) -> Strand
+{
+    let mut step = SolutionStep::new(start, None, 0);
+    while step.spliceCount < maxSplices {
+        let (mutations, splices): (Vec<_>, Vec<_>) = makeSuccessors(&step, maxSplices, options).into_iter()
+            .map(|(successor, _cost)| successor)
+            .partition(|successor| matches!(successor.lastAction, Some(Action::Mutate{..})));
+        let candidates = match (mutations.is_empty(), splices.is_empty()) {
+            (false, true) => mutations,
+            (false, false) if random.below(MUTATION_CHANCE) == 0 => mutations,
+            (_, false) => splices,
+            (true, true) => break
+        };
+        let index = random.below(candidates.len());
+        step = candidates.into_iter().nth(index).unwrap();
+    }
+    step.strand
+}
+
+// The SplitMix64 generator, which is enough for picking random actions and keeps generated levels reproducible.
+struct Random
+{
+    state: u64
+}
+
+impl Random
+{
+    fn new(seed: u64) -> Self
+    {
+        Self{state: seed}
+    }
+
+    fn next(&mut self) -> u64
+    {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+
+    // The slight bias of the modulo does not matter for generating levels.
+    fn below(&mut self, bound: usize) -> usize
+    {
+        (self.next() % bound.try_to::<u64>().unwrap()).try_to().unwrap()
+    }
+
+    fn shuffle<T>(&mut self, mut items: Vec<T>) -> Vec<T>
+    {
+        for index in (1..items.len()).rev() {
+            items.swap(index, self.below(index + 1));
+        }
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::level_solver::SearchEnd;
+
+    const SETTINGS: GeneratorSettings =
+        GeneratorSettings{nodeCount: 7, doublerCount: 1, extenderCount: 1, eraserCount: 0, minOptimalSplices: 2};
+
+    #[test]
+    fn sameSeedGeneratesTheSameLevel()
+    {
+        let options = SolverOptions::default();
+        let shapes = |level: Level| (level.start.canonicalShape(), level.target.canonicalShape());
+        let levels = (0..3).map(|seed| shapes(generateLevel(&SETTINGS, seed, &options).unwrap())).collect_vec();
+        for (seed, level) in (0..3).zip(&levels) {
+            assert_eq!(shapes(generateLevel(&SETTINGS, seed, &options).unwrap()), *level);
+        }
+        assert!(levels.iter().all_unique());
+    }
+
+    #[test]
+    fn generatedLevelsNeedTheirOptimalSplices()
+    {
+        let options = SolverOptions{costModel: CostModel::FewestSplices, ..SolverOptions::default()};
+        for seed in 0..3 {
+            let level = generateLevel(&SETTINGS, seed, &options).unwrap();
+            let optimalSplices = level.optimalSplices.unwrap();
+            assert!(optimalSplices >= SETTINGS.minOptimalSplices);
+            assert_eq!(level.maxSplices, optimalSplices);
+
+            let Level{start, target, ..} = level;
+            let (result, _statistics) = solveLevelWithStatistics(
+                Level{start: start.clone(), target: target.clone(), maxSplices: optimalSplices, optimalSplices: None},
+                &options);
+            assert_eq!(result.unwrap().spliceCount, optimalSplices);
+            let (result, _statistics) = solveLevelWithStatistics(
+                Level{start, target, maxSplices: optimalSplices - 1, optimalSplices: None}, &options);
+            assert_eq!(result.unwrap_err().searchEnd, SearchEnd::Exhausted);
+        }
+    }
+}
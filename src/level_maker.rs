@@ -1,228 +1,236 @@
+use crate::level_file::{LevelDefinition, loadLevelFile, parseLevelFile};
 use crate::strand::{CellKind, Edge, NodeId, Strand};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use itertools::Itertools as _;
+use std::path::Path;
 
 
-pub(crate) fn makeLevel(sequence: SequenceNumber, strand: StrandNumber) -> Result<Level>
+// The sequences and levels of the level files, loaded once.
+pub(crate) struct LevelCatalog
 {
-    if sequence.0 == 0 {
-        bail!("Sequence number must start at 1, got 0.")
+    sequences: Vec<SequenceEntry>,
+    levels: Vec<LevelDefinition>
+}
+
+impl LevelCatalog
+{
+    pub(crate) fn load() -> Result<Self>
+    {
+        let levels = loadLevelDefinitions()?;
+        let mut sequences: Vec<SequenceEntry> = vec![];
+        for level in &levels {
+            let metadata = makeLevelMetadata(level);
+            match sequences.last_mut() {
+                Some(sequence) if sequence.number == level.sequence => sequence.levels.push(metadata),
+                _ => {
+                    let expectedSequence = sequences.len() + 1;
+                    if usize::from(level.sequence.0) != expectedSequence {
+                        bail!("Sequence {} is missing, sequences must be numbered without gaps.", expectedSequence);
+                    }
+                    sequences.push(SequenceEntry{number: level.sequence, levels: vec![metadata]});
+                }
+            }
+        }
+        if sequences.is_empty() {
+            bail!("No level was found.");
+        }
+        Ok(Self{sequences, levels})
     }
-    if strand.0 == 0 {
-        bail!("Strand number must start at 1, got 0.")
+
+    pub(crate) fn sequences(&self) -> &[SequenceEntry]
+    {
+        &self.sequences
     }
 
-    match sequence.0 {
-        1 => makeStrandInSequence1(strand),
-        2 => makeStrandInSequence2(strand),
-        3 => makeStrandInSequence3(strand),
-        4 => makeStrandInSequence4(strand),
-        5 => makeStrandInSequence5(strand),
-        _ => bail!("Unsupported sequence number: {}", sequence.0)
+    pub(crate) fn makeLevel(&self, sequence: SequenceNumber, strand: StrandNumber) -> Result<Level>
+    {
+        makeLevelFrom(&self.findLevel(sequence, strand)?.info)
+            .with_context(|| format!("Strand {}-{} is invalid", sequence.0, strand.0))
     }
-}
 
-fn makeStrandInSequence1(strand: StrandNumber) -> Result<Level>
-{
-    let levelInfo = match strand.0 {
-        1 => LevelInfo{
-            start:  StrandInfo{nodeCount: 3, edges: vec![(0,1), (1,2)], mutables: vec![]},
-            target: StrandInfo{nodeCount: 3, edges: vec![(0,1), (0,2)], mutables: vec![]},
-            maxSplices: 1},
-        2 => LevelInfo{
-            start:  StrandInfo{nodeCount: 5, edges: vec![(0,1), (1,2), (2,3), (1,4)], mutables: vec![]},
-            target: StrandInfo{nodeCount: 5, edges: vec![(0,1), (1,2), (0,3), (3,4)], mutables: vec![]},
-            maxSplices: 1}, // really 2, but it's angelic
-        3 => LevelInfo{
-            start:  StrandInfo{nodeCount: 11, edges: vec![(0,1), (1,2), (1,3), (3,4), (3,5), (0,6), (6,7), (6,8), (8,9), (8,10)], mutables: vec![]},
-            target: StrandInfo{nodeCount: 11, edges: vec![(0,1), (1,2), (2,3), (3,4), (3,5), (0,6), (6,7), (7,8), (8,9), (8,10)], mutables: vec![]},
-            maxSplices: 2},
-        4 => LevelInfo{
-            start:  StrandInfo{nodeCount: 11, edges: vec![(0,1), (1,2), (2,3), (2,4), (4,5), (1,6), (6,7), (7,8), (6,9), (0,10)], mutables: vec![]},
-            target: StrandInfo{nodeCount: 11, edges: vec![(0,1), (1,2), (2,3), (3,4), (2,5), (0,6), (6,7), (7,8), (7,9), (9,10)], mutables: vec![]},
-            maxSplices: 1},
-        5 => LevelInfo{
-            start:  StrandInfo{nodeCount: 10, edges: vec![(0,1), (1,2), (1,3), (0,4), (4,5), (4,6), (6,7), (7,8), (7,9)], mutables: vec![]},
-            target: StrandInfo{nodeCount: 10, edges: vec![(0,1), (1,2), (2,3), (3,4), (3,5), (1,6), (6,7), (7,8), (7,9)], mutables: vec![]},
-            maxSplices: 1}, // really 2, but it's angelic
-        6 => LevelInfo{
-            start:  StrandInfo{nodeCount: 13, edges: vec![(0,1), (1,2), (1,3), (3,4), (4,5), (4,6), (0,7), (7,8), (8,9), (9,10), (9,11),  (7,12)], mutables: vec![]},
-            target: StrandInfo{nodeCount: 13, edges: vec![(0,1), (1,2), (2,3), (3,4), (3,5), (1,6), (0,7), (7,8), (7,9), (9,10), (10,11), (10,12)], mutables: vec![]},
-            maxSplices: 1}, // really 2, but it's angelic
-        7 => LevelInfo{
-            start:  StrandInfo{nodeCount: 13, edges: vec![(0,1), (1,2), (2,3), (3,4), (3,5), (5,6), (5,7), (2,8), (8,9), (9,10), (9,11), (8,12)], mutables: vec![]},
-            target: StrandInfo{nodeCount: 13, edges: vec![(0,1), (1,2), (2,3), (2,4), (4,5), (4,6), (0,7), (7,8), (8,9), (9,10), (9,11), (8,12)], mutables: vec![]},
-            maxSplices: 2},
-        _ => bail!("Unsupported strand number: {}", strand.0)
-    };
-    Ok(makeLevelFrom(levelInfo))
-}
+    pub(crate) fn findLevelMetadata(&self, sequence: SequenceNumber, strand: StrandNumber) -> Result<LevelMetadata>
+    {
+        Ok(makeLevelMetadata(self.findLevel(sequence, strand)?))
+    }
 
-fn makeStrandInSequence2(strand: StrandNumber) -> Result<Level>
-{
-    use CellKind::Doubler;
-    let levelInfo = match strand.0 {
-        1 => LevelInfo{
-            start:  StrandInfo{nodeCount: 3, edges: vec![(0,1), (1,2)], mutables: vec![(1, Doubler)]},
-            target: StrandInfo{nodeCount: 5, edges: vec![(0,1), (1,2), (0,3), (3,4)], mutables: vec![]},
-            maxSplices: 1},
-        2 => LevelInfo{
-            start:  StrandInfo{nodeCount: 4, edges: vec![(0,1), (0,2), (2,3)], mutables: vec![(3, Doubler)]},
-            target: StrandInfo{nodeCount: 6, edges: vec![(0,1), (1,2), (2,3), (1,4), (4,5)], mutables: vec![]},
-            maxSplices: 1},
-        3 => LevelInfo{
-            start:  StrandInfo{nodeCount: 5, edges: vec![(0,1), (1,2), (1,3), (3,4)], mutables: vec![(4, Doubler)]},
-            target: StrandInfo{nodeCount: 7, edges: vec![(0,1), (1,2), (2,3), (0,4), (4,5), (5,6)], mutables: vec![]},
-            maxSplices: 3},
-        4 => LevelInfo{
-            start:  StrandInfo{nodeCount: 8,  edges: vec![(0,1), (1,2), (2,3), (2,4), (4,5), (1,6), (6,7)], mutables: vec![(1, Doubler)]},
-            target: StrandInfo{nodeCount: 15, edges: vec![(0,1), (1,2), (2,3), (2,4), (1,5), (5,6), (5,7), (0,8), (8,9), (9,10), (9,11), (8,12), (12,13), (12,14)], mutables: vec![]},
-            maxSplices: 1}, // really 2, but it's angelic
-        5 => LevelInfo{
-            start:  StrandInfo{nodeCount: 5, edges: vec![(0,1), (1,2), (2,3), (1,4)], mutables: vec![(3, Doubler)]},
-            target: StrandInfo{nodeCount: 8, edges: vec![(0,1), (1,2), (2,3), (3,4), (1,5), (5,6), (6,7)], mutables: vec![]},
-            maxSplices: 2}, // really 3, but it's angelic
-        6 => LevelInfo{
-            start:  StrandInfo{nodeCount: 8,  edges: vec![(0,1), (1,2), (2,3), (3,4), (3,5), (1,6), (6,7)], mutables: vec![(3, Doubler)]},
-            target: StrandInfo{nodeCount: 12, edges: vec![(0,1), (1,2), (2,3), (3,4), (4,5), (3,6), (1,7), (7,8), (8,9), (8,10), (10,11)], mutables: vec![]},
-            maxSplices: 3},
-        7 => LevelInfo{
-            start:  StrandInfo{nodeCount: 3, edges: vec![(0,1), (1,2)], mutables: vec![(1, Doubler), (2, Doubler)]},
-            target: StrandInfo{nodeCount: 9, edges: vec![(0,1), (1,2), (2,3), (2,4), (0,5), (5,6), (6,7), (6,8)], mutables: vec![]},
-            maxSplices: 2},
-        _ => bail!("Unsupported strand number: {}", strand.0)
-    };
-    Ok(makeLevelFrom(levelInfo))
+    fn findLevel(&self, sequence: SequenceNumber, strand: StrandNumber) -> Result<&LevelDefinition>
+    {
+        if !self.sequences.iter().any(|entry| entry.number == sequence) {
+            bail!("Unsupported sequence number: {}", sequence.0);
+        }
+        match self.levels.iter().find(|level| level.sequence == sequence && level.strand == strand) {
+            Some(level) => Ok(level),
+            None => bail!("Unsupported strand number: {}", strand.0)
+        }
+    }
 }
 
-fn makeStrandInSequence3(strand: StrandNumber) -> Result<Level>
+// Returns the levels of every sequence ordered by their numbers. A sequence may be defined in any level file,
+// but only in one of them.
+fn loadLevelDefinitions() -> Result<Vec<LevelDefinition>>
 {
-    use CellKind::{Doubler, Extender};
-    let levelInfo = match strand.0 {
-        1 => LevelInfo{
-            start:  StrandInfo{nodeCount: 4, edges: vec![(0,1), (1,2), (1,3)], mutables: vec![(1, Doubler), (3, Extender)]},
-            target: StrandInfo{nodeCount: 9, edges: vec![(0,1), (1,2), (1,3), (3,4), (0,5), (5,6), (6,7), (5,8)], mutables: vec![]},
-            maxSplices: 1},
-        2 => LevelInfo{
-            start:  StrandInfo{nodeCount: 6, edges: vec![(0,1), (1,2), (1,3), (3,4), (4,5)], mutables: vec![(1, Extender), (4, Extender), (5, Extender)]},
-            target: StrandInfo{nodeCount: 9, edges: vec![(0,1), (1,2), (2,3), (2,4), (0,5), (5,6), (6,7), (6,8)], mutables: vec![]},
-            maxSplices: 2}, // really 3, but it's angelic
-        3 => LevelInfo{
-            start:  StrandInfo{nodeCount: 5,  edges: vec![(0,1), (1,2), (2,3), (3,4)], mutables: vec![(1, Doubler), (4, Extender)]},
-            target: StrandInfo{nodeCount: 11, edges: vec![(0,1), (1,2), (2,3), (1,4), (4,5), (0,6), (6,7), (7,8), (6,9), (9,10)], mutables: vec![]},
-            maxSplices: 1}, // really 2, but it's angelic
-        4 => LevelInfo{
-            start:  StrandInfo{nodeCount: 7,  edges: vec![(0,1), (1,2), (1,3), (0,4), (4,5), (5,6)], mutables: vec![(4, Extender), (5, Doubler)]},
-            target: StrandInfo{nodeCount: 13, edges: vec![(0,1), (1,2), (2,3), (3,4), (4,5), (3,6), (6,7), (2, 8), (8,9), (9,10), (8,11), (11,12)], mutables: vec![]},
-            maxSplices: 2}, // really 3, but it's angelic
-        5 => LevelInfo{
-            start:  StrandInfo{nodeCount: 7,  edges: vec![(0,1), (0,2), (2,3), (3,4), (2,5), (5,6)], mutables: vec![(1, Extender), (4, Doubler), (6, Doubler)]},
-            target: StrandInfo{nodeCount: 10, edges: vec![(0,1), (1,2), (2,3), (2,4), (4,5), (1,6), (6,7), (7,8), (6,9)], mutables: vec![]},
-            maxSplices: 2},
-        6 => LevelInfo{
-            start:  StrandInfo{nodeCount: 6, edges: vec![(0,1), (1,2), (1,3), (3,4), (4,5)], mutables: vec![(3, Extender), (4, Doubler), (5, Doubler)]},
-            target: StrandInfo{nodeCount: 9, edges: vec![(0,1), (1,2), (2,3), (2,4), (0,5), (5,6), (6,7), (6,8)], mutables: vec![]},
-            maxSplices: 2},
-        7 => LevelInfo{
-            start:  StrandInfo{nodeCount: 6,  edges: vec![(0,1), (0,2), (2,3), (3,4), (4,5)], mutables: vec![(3, Doubler), (4, Doubler), (5, Extender)]},
-            target: StrandInfo{nodeCount: 13, edges: vec![(0,1), (1,2), (2,3), (1,4), (4,5), (0,6), (6,7), (7,8), (7,9), (6,10), (10,11), (10,12)], mutables: vec![]},
-            maxSplices: 2},
-        _ => bail!("Unsupported strand number: {}", strand.0)
-    };
-    Ok(makeLevelFrom(levelInfo))
+    let mut levels = vec![];
+    let mut sequenceFiles: Vec<(SequenceNumber, String)> = vec![];
+    for (fileName, fileLevels) in loadLevelFiles()? {
+        for sequence in fileLevels.iter().map(|level| level.sequence).dedup() {
+            let otherFileOpt = sequenceFiles.iter().find(|(otherSequence, _)| *otherSequence == sequence);
+            if let Some((_, otherFileName)) = otherFileOpt {
+                bail!("Sequence {} is defined in both {} and {}.", sequence.0, otherFileName, fileName);
+            }
+            sequenceFiles.push((sequence, fileName.clone()));
+        }
+        levels.extend(fileLevels);
+    }
+    levels.sort_by_key(|level| (level.sequence.0, level.strand.0));
+    Ok(levels)
 }
 
-fn makeStrandInSequence4(strand: StrandNumber) -> Result<Level>
+// The level files of the directory named by the environment variable replace the built-in ones, so that levels
+// can be corrected without rebuilding the program.
+fn loadLevelFiles() -> Result<Vec<(String, Vec<LevelDefinition>)>>
 {
-    use CellKind::Eraser;
-    let levelInfo = match strand.0 {
-        1 => LevelInfo{
-            start:  StrandInfo{nodeCount: 6, edges: vec![(0,1), (1,2), (1,3), (0,4), (4,5)], mutables: vec![(2, Eraser), (4, Eraser)]},
-            target: StrandInfo{nodeCount: 3, edges: vec![(0,1), (1,2)], mutables: vec![]},
-            maxSplices: 0},
-        2 => LevelInfo{
-            start:  StrandInfo{nodeCount: 7, edges: vec![(0,1), (1,2), (1,3), (0,4), (4,5), (4,6)], mutables: vec![(2, Eraser)]},
-            target: StrandInfo{nodeCount: 3, edges: vec![(0,1), (1,2)], mutables: vec![]},
-            maxSplices: 1},
-        3 => LevelInfo{
-            start:  StrandInfo{nodeCount: 11, edges: vec![(0,1), (1,2), (1,3), (3,4), (3,5), (0,6), (6,7), (7,8), (7,9), (6,10)], mutables: vec![(5, Eraser)]},
-            target: StrandInfo{nodeCount: 9,  edges: vec![(0,1), (1,2), (2,3), (2,4), (0,5), (5,6), (6,7), (6,8)], mutables: vec![]},
-            maxSplices: 2}, // really 3, but it's angelic
-        4 => LevelInfo{
-            start:  StrandInfo{nodeCount: 11, edges: vec![(0,1), (1,2), (1,3), (0,4), (4,5), (5,6), (4,7), (7,8), (8,9), (7,10)], mutables: vec![(2, Eraser), (4, Eraser), (6, Eraser)]},
-            target: StrandInfo{nodeCount: 6,  edges: vec![(0,1), (1,2), (2,3), (1,4), (4,5)], mutables: vec![]},
-            maxSplices: 3},
-        5 => LevelInfo{
-            start:  StrandInfo{nodeCount: 12, edges: vec![(0,1), (1,2), (2,3), (2,4), (4,5), (1,6), (6,7), (7,8), (8,9), (8,10), (7,11)], mutables: vec![(6, Eraser), (8, Eraser)]},
-            target: StrandInfo{nodeCount: 10, edges: vec![(0,1), (1,2), (2,3), (3,4), (2,5), (1,6), (6,7), (6,8), (8,9)], mutables: vec![]},
-            maxSplices: 4}, // really 5, but it's angelic
-        6 => LevelInfo{
-            start:  StrandInfo{nodeCount: 21, edges: vec![(0,1), (1,2), (2,3), (2,4), (4,5), (5,6), (6,7), (6,8), (1,9), (9,10), (10,11), (11,12), (11,13), (9,14), (0,15), (15,16), (15,17), (17,18), (18,19), (18,20)], mutables: vec![(5, Eraser), (16, Eraser)]},
-            target: StrandInfo{nodeCount: 15, edges: vec![(0,1), (1,2), (2,3), (2,4), (1,5), (5,6), (5,7), (0,8), (8,9), (9,10), (9,11), (8,12), (12,13), (12,14)], mutables: vec![]},
-            maxSplices: 3},
-        7 => LevelInfo{
-            start:  StrandInfo{nodeCount: 16, edges: vec![(0,1), (1,2), (2,3), (3,4), (4,5), (3,6), (2,7), (7,8), (1,9), (9,10), (10,11), (9,12), (12,13), (12,14), (14,15)], mutables: vec![(2, Eraser), (12, Eraser)]},
-            target: StrandInfo{nodeCount: 11, edges: vec![(0,1), (1,2), (2,3), (3,4), (2,5), (0,6), (6,7), (7,8), (7,9), (9,10)], mutables: vec![]},
-            maxSplices: 3}, // really 4, but it's angelic
-        _ => bail!("Unsupported strand number: {}", strand.0)
+    let Some(directory) = std::env::var_os(LEVEL_DIRECTORY_VARIABLE) else {
+        return BUILT_IN_LEVEL_FILES.iter()
+            .map(|(fileName, text)| {
+                let levels = parseLevelFile(text)
+                    .with_context(|| format!("Built-in level file {} is invalid", fileName))?;
+                Ok((fileName.to_string(), levels))
+            })
+            .collect();
     };
-    Ok(makeLevelFrom(levelInfo))
+    let directory = Path::new(&directory);
+    let mut paths = std::fs::read_dir(directory)
+        .and_then(|entries| entries.map(|entry| Ok(entry?.path())).collect::<std::io::Result<Vec<_>>>())
+        .with_context(|| format!("Failed to read level directory {}", directory.display()))?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "txt"));
+    paths.sort();
+    paths.into_iter().map(|path| Ok((path.display().to_string(), loadLevelFile(&path)?))).collect()
 }
 
-fn makeStrandInSequence5(strand: StrandNumber) -> Result<Level>
+fn makeLevelMetadata(level: &LevelDefinition) -> LevelMetadata
 {
-    use CellKind::{Doubler, Eraser, Extender};
-    let levelInfo = match strand.0 {
-        1 => LevelInfo{
-            start:  StrandInfo{nodeCount: 7, edges: vec![(0,1), (1,2), (2,3), (2,4), (0,5), (5,6)], mutables: vec![(2, Doubler), (3, Extender), (4, Eraser)]},
-            target: StrandInfo{nodeCount: 7, edges: vec![(0,1), (1,2), (1,3), (0,4), (4,5), (4,6)], mutables: vec![]},
-            maxSplices: 2}, // really 3, but it's angelic
-        2 => LevelInfo{
-            start:  StrandInfo{nodeCount: 7, edges: vec![(0,1), (1,2), (2,3), (3,4), (1,5), (5,6)], mutables: vec![(1, Eraser), (2, Extender), (3, Doubler)]},
-            target: StrandInfo{nodeCount: 9, edges: vec![(0,1), (1,2), (2,3), (3,4), (3,5), (2,6), (6,7), (6,8)], mutables: vec![]},
-            maxSplices: 2},
-        _ => bail!("Unsupported strand number: {}", strand.0)
+    let name = match &level.name {
+        Some(name) => name.clone(),
+        None => format!("Strand {}-{}", level.sequence.0, level.strand.0)
     };
-    Ok(makeLevelFrom(levelInfo))
+    LevelMetadata{strand: level.strand, name, par: level.info.maxSplices}
 }
 
-fn makeLevelFrom(levelInfo: LevelInfo) -> Level
+pub(crate) fn makeLevelFrom(levelInfo: &LevelInfo) -> Result<Level>
 {
-    let start = makeStrand(&levelInfo.start);
-    let target = makeStrand(&levelInfo.target);
-    Level{start, target, maxSplices: levelInfo.maxSplices}
+    let start = makeStrand(&levelInfo.start).context("Invalid start strand")?;
+    let target = makeStrand(&levelInfo.target).context("Invalid target strand")?;
+    if let Some(optimalSplices) = levelInfo.optimalSplices {
+        if optimalSplices > levelInfo.maxSplices {
+            bail!("Optimal splice count {} exceeds the splice allowance {}.", optimalSplices, levelInfo.maxSplices);
+        }
+    }
+    Ok(Level{start, target, maxSplices: levelInfo.maxSplices, optimalSplices: levelInfo.optimalSplices})
 }
 
-fn makeStrand(strandInfo: &StrandInfo) -> Strand
+fn makeStrand(strandInfo: &StrandInfo) -> Result<Strand>
 {
-    Strand::new(strandInfo.nodeCount, &strandInfo.edges, &strandInfo.mutables)
+    Strand::tryNew(strandInfo.nodeCount, &strandInfo.edges, &strandInfo.mutables)
 }
 
 pub(crate) struct Level
 {
     pub start: Strand,
     pub target: Strand,
-    pub maxSplices: SpliceCount
+    pub maxSplices: SpliceCount,
+    pub optimalSplices: Option<SpliceCount>
 }
 
 pub(crate) type SpliceCount = u8;
 
-#[derive(Clone, Copy)]
+pub(crate) const LEVEL_DIRECTORY_VARIABLE: &str = "SPLICE_SOLVER_LEVELS";
+
+include!(concat!(env!("OUT_DIR"), "/built_in_levels.rs"));
+
+pub(crate) struct SequenceEntry
+{
+    pub number: SequenceNumber,
+    pub levels: Vec<LevelMetadata>
+}
+
+impl SequenceEntry
+{
+    pub(crate) fn strandCount(&self) -> usize
+    {
+        self.levels.len()
+    }
+}
+
+pub(crate) struct LevelMetadata
+{
+    pub strand: StrandNumber,
+    pub name: String,
+    pub par: SpliceCount
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) struct SequenceNumber(pub u8);
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) struct StrandNumber(pub u8);
 
-struct LevelInfo
+pub(crate) struct LevelInfo
+{
+    pub start: StrandInfo,
+    pub target: StrandInfo,
+    pub maxSplices: SpliceCount,
+    pub optimalSplices: Option<SpliceCount>
+}
+
+pub(crate) struct StrandInfo
 {
-    start: StrandInfo,
-    target: StrandInfo,
-    maxSplices: SpliceCount
+    pub nodeCount: usize,
+    pub edges: Vec<Edge>,
+    pub mutables: Vec<(NodeId, CellKind)>
 }
 
-struct StrandInfo
+#[cfg(test)]
+mod tests
 {
-    nodeCount: usize,
-    edges: Vec<Edge>,
-    mutables: Vec<(NodeId, CellKind)>
+    use super::*;
+    use crate::cost_model::CostModel;
+    use crate::level_solver::{solveLevelWithStatistics, SolverOptions, SpliceRating};
+    use crate::rule_set::RuleSet;
+
+    #[test]
+    fn catalogNumbersSequencesAndStrandsWithoutGaps()
+    {
+        let catalog = LevelCatalog::load().unwrap();
+        for (sequenceIndex, sequence) in catalog.sequences().iter().enumerate() {
+            assert_eq!(usize::from(sequence.number.0), sequenceIndex + 1);
+            for (levelIndex, metadata) in sequence.levels.iter().enumerate() {
+                assert_eq!(usize::from(metadata.strand.0), levelIndex + 1);
+                assert_eq!(catalog.findLevelMetadata(sequence.number, metadata.strand).unwrap().name, metadata.name);
+            }
+            let missingStrand = StrandNumber(u8::try_from(sequence.strandCount()).unwrap() + 1);
+            assert!(catalog.makeLevel(sequence.number, missingStrand).is_err());
+        }
+        let missingSequence = SequenceNumber(u8::try_from(catalog.sequences().len()).unwrap() + 1);
+        assert!(catalog.makeLevel(missingSequence, StrandNumber(1)).is_err());
+    }
+
+    #[test]
+    fn builtInLevelsAreSolvableWithTheirOptimalSplices()
+    {
+        let catalog = LevelCatalog::load().unwrap();
+        for rules in [RuleSet::default(), RuleSet::GAME] {
+            let options = SolverOptions{costModel: CostModel::FewestSplices, rules, ..SolverOptions::default()};
+            for sequence in catalog.sequences() {
+                for metadata in &sequence.levels {
+                    let level = catalog.makeLevel(sequence.number, metadata.strand).unwrap();
+                    let optimalSplices = level.optimalSplices.unwrap();
+                    let (result, _statistics) = solveLevelWithStatistics(level, &options);
+                    let solution = result.unwrap_or_else(|_| panic!("{} has no solution", metadata.name));
+                    assert_eq!(solution.spliceRating, SpliceRating::Optimal, "{}", metadata.name);
+                    assert_eq!(solution.spliceCount, optimalSplices, "{}", metadata.name);
+                }
+            }
+        }
+    }
 }
@@ -1,84 +1,778 @@
+use crate::cost_model::{ActionCounts, Cost, CostModel, primaryCost};
+use crate::dead_end_detector::DeadEndDetector;
+use crate::heuristic::StrandHeuristic;
 use crate::level_maker::{Level, SpliceCount};
-use crate::strand::{CellKind, NodeId, Strand};
+use crate::rule_set::{RuleSet, SwapRule};
+use crate::search_limits::{CancellationToken, SearchLimit, SearchLimits};
+use crate::strand::{CanonicalShape, ChildSlot, MAX_NODE_COUNT, NodeId, Strand, SurfaceMatching};
+use crate::strand_pattern::StrandPattern;
 
-use pathfinding::directed::dijkstra::dijkstra;
+use itertools::Itertools as _;
+use pathfinding::directed::yen::yen;
 use petgraph::visit::Dfs;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::collections::hash_map::Entry;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+use to_trait::To;
 
 
 const NO_LAST_ACTION: Option<Action> = None;
 const START_SPLICE_COUNT: SpliceCount = 0;
+const STATES_PER_THREAD: usize = 32;
+const DEFAULT_TRANSPOSITION_TABLE_CAPACITY: usize = 1 << 20;
+const MAX_PERIMETER_SIZE: usize = 1 << 20;
 
-pub(crate) fn solveLevel(level: Level) -> Option<Vec<SolutionStep>>
+pub(crate) fn solveLevelWithStatistics(level: Level, options: &SolverOptions)
+    -> (Result<Solution, NoSolution>, SearchStatistics)
 {
-    let startStep = SolutionStep::new(level.start, NO_LAST_ACTION, START_SPLICE_COUNT);
-    let result = dijkstra(
-        &startStep, |step| makeSuccessors(step, level.maxSplices), |step| isGoalReached(step, &level.target));
-    result.map(|(nodes, _cost)| nodes)
+    let mut progress = SearchProgress::new(&level.start, Some(&level.target), options);
+    let deadEnds = DeadEndDetector::new(&level.target, options.pruneDeadEnds);
+    let steps = match deadEnds.isDeadEnd(&level.start) {
+        true => None,
+        false => searchWithinSpliceLimits(&level, options.costModel, |maxSplices| {
+            solveWithinSplices(&level, maxSplices, options, &deadEnds, &mut progress)
+        })
+    };
+    progress.recordDeadEnds(&deadEnds);
+    finishSearch(steps, level.optimalSplices, progress)
+}
+
+// No estimate of the remaining cost is known for a pattern, so it is always searched by Dijkstra's algorithm.
+pub(crate) fn solvePatternWithStatistics(
+    start: Strand, pattern: &StrandPattern, maxSplices: SpliceCount, options: &SolverOptions)
+    -> (Result<Solution, NoSolution>, SearchStatistics)
+{
+    let mut progress = SearchProgress::new(&start, None, options);
+    let startStep = SolutionStep::new(start.clone(), NO_LAST_ACTION, START_SPLICE_COUNT);
+    let steps = searchBestFirst(
+        startStep,
+        0,
+        |step| withoutEstimates(makeSuccessors(step, maxSplices, options)),
+        |step| isGoalReached(step, &Goal::Pattern(pattern), options.matching),
+        options.threadCount,
+        &mut progress);
+    let steps = steps.map(|path| rebuildPath(path, maxSplices, options));
+    finishSearch(steps, None, progress)
+}
+
+fn finishSearch(steps: Option<Vec<SolutionStep>>, optimalSplices: Option<SpliceCount>, mut progress: SearchProgress)
+    -> (Result<Solution, NoSolution>, SearchStatistics)
+{
+    progress.statistics.wallTime = progress.startTime.elapsed();
+    let statistics = progress.statistics.clone();
+    let result = match steps {
+        Some(steps) => Ok(Solution::new(steps, optimalSplices)),
+        None => Err(progress.intoNoSolution())
+    };
+    (result, statistics)
+}
+
+// Returns distinct solutions ordered from the cheapest one, where solutions going through the same shapes are
+// considered the same.
+pub(crate) fn enumerateSolutions(level: Level, options: &SolverOptions, selection: SolutionSelection)
+    -> Result<Vec<Solution>, NoSolution>
+{
+    let mut progress = SearchProgress::new(&level.start, Some(&level.target), options);
+    let deadEnds = DeadEndDetector::new(&level.target, options.pruneDeadEnds);
+    let paths = match deadEnds.isDeadEnd(&level.start) {
+        true => None,
+        // Worse solutions may need more splices than the optimal ones, so they are searched within the allowance.
+        false => match selection {
+            SolutionSelection::AllOptimal{..} => searchWithinSpliceLimits(&level, options.costModel, |maxSplices| {
+                let paths = findPaths(&level, maxSplices, options, selection, &deadEnds, &mut progress);
+                (!paths.is_empty()).then_some(paths)
+            }),
+            SolutionSelection::Best{..} => {
+                let paths = findPaths(&level, level.maxSplices, options, selection, &deadEnds, &mut progress);
+                (!paths.is_empty()).then_some(paths)
+            }
+        }
+    };
+    // Paths found by an interrupted search are not guaranteed to be the best ones.
+    let paths = match paths {
+        Some(paths) if !progress.isInterrupted() => paths,
+        _ => return Err(progress.intoNoSolution())
+    };
+    let mut solutions = paths.into_iter()
+        .unique_by(|path| path.iter().map(|step| step.shape.clone()).collect_vec())
+        .map(|path| Solution::new(path, level.optimalSplices))
+        .collect_vec();
+    solutions.sort_by_cached_key(|solution| (
+        options.costModel.cost(solution.actionCounts()),
+        solution.steps.iter().map(|step| format!("{:?}", step.lastAction)).collect_vec()));
+    Ok(solutions)
+}
+
+// Finds the best next action for a strand reached partway through a level, with the splices already used
+// no longer available.
+pub(crate) fn findHint(strand: Strand, target: Strand, maxSplices: SpliceCount, usedSplices: SpliceCount, options: &SolverOptions)
+    -> Result<Hint, NoSolution>
+{
+    let level = Level{start: strand, target, maxSplices: maxSplices.saturating_sub(usedSplices), optimalSplices: None};
+    let (result, _statistics) = solveLevelWithStatistics(level, options);
+    let solution = result?;
+    Ok(Hint{
+        nextStepOpt: solution.steps.get(1).cloned(),
+        remainingSteps: solution.stepCount(),
+        remainingSplices: solution.spliceCount,
+        remainingMutations: solution.mutationCount})
+}
+
+// The optimal splice count is a tighter bound than the allowance, so trying it first keeps the search small.
+// This is only right when splices are minimized, other cost models may find cheaper paths using more splices.
+fn searchWithinSpliceLimits<T>(
+    level: &Level, costModel: CostModel, mut search: impl FnMut(SpliceCount) -> Option<T>)
+    -> Option<T>
+{
+    if let (Some(optimalSplices), CostModel::FewestSplices) = (level.optimalSplices, costModel) {
+        if let Some(result) = search(optimalSplices) {
+            return Some(result);
+        }
+    }
+    search(level.maxSplices)
 }
 
-fn makeSuccessors(solutionStep: &SolutionStep, maxSplices: SpliceCount) -> Vec<StepAndCost>
+fn solveWithinSplices(
+    level: &Level, maxSplices: SpliceCount, options: &SolverOptions, deadEnds: &DeadEndDetector,
+    progress: &mut SearchProgress)
+    -> Option<Vec<SolutionStep>>
 {
+    let startStep = SolutionStep::new(level.start.clone(), NO_LAST_ACTION, START_SPLICE_COUNT);
+    let path = match options.algorithm {
+        SearchAlgorithm::Dijkstra => searchBestFirst(
+            startStep,
+            0,
+            |step| withoutEstimates(makeLiveSuccessors(step, maxSplices, options, deadEnds)),
+            |step| isGoalReached(step, &Goal::Target(&level.target), options.matching),
+            options.threadCount,
+            progress),
+        SearchAlgorithm::Bidirectional if options.rules.swapRule == SwapRule::Splice => {
+            if level.target.canonicalShape().hasSpecialCells() {
+                progress.statistics.bidirectionalFallbackOpt = Some(BidirectionalFallback::SpecialCellsInTarget);
+            }
+            let heuristic = StrandHeuristic::new(&level.target, options.rules.swapRule, options.matching);
+            let perimeter = TargetPerimeter::new(&level.target, maxSplices.div_ceil(2), options, progress)?;
+            let estimateRemainingActions =
+                |step: &SolutionStep| perimeter.estimateRemainingActions(step, maxSplices, &heuristic);
+            let startRemainingCost = options.costModel.cost(estimateRemainingActions(&startStep).unwrap_or_default());
+            let forwardPath = searchBestFirst(
+                startStep,
+                startRemainingCost,
+                |step| {
+                    let successors = makeLiveSuccessors(step, maxSplices, options, deadEnds);
+                    estimateSuccessors(successors, estimateRemainingActions, options.costModel)
+                },
+                |step| perimeter.isWithinReach(step, maxSplices),
+                options.threadCount,
+                progress);
+            forwardPath.map(|forwardPath| perimeter.extendPath(forwardPath, maxSplices, options))
+        },
+        // The target perimeter needs every splice to cost the same, so free swaps make it fall back to A*.
+        SearchAlgorithm::AStar | SearchAlgorithm::Bidirectional => {
+            if options.algorithm == SearchAlgorithm::Bidirectional {
+                progress.statistics.bidirectionalFallbackOpt = Some(BidirectionalFallback::FreeSwaps);
+            }
+            let heuristic = StrandHeuristic::new(&level.target, options.rules.swapRule, options.matching);
+            let startRemainingCost = estimateRemainingCost(&startStep, maxSplices, options.costModel, &heuristic);
+            searchBestFirst(
+                startStep,
+                startRemainingCost,
+                |step| makeEstimatedSuccessors(step, maxSplices, options, deadEnds, &heuristic),
+                |step| isGoalReached(step, &Goal::Target(&level.target), options.matching),
+                options.threadCount,
+                progress)
+        },
+        SearchAlgorithm::IdaStar => {
+            let heuristic = StrandHeuristic::new(&level.target, options.rules.swapRule, options.matching);
+            let startRemainingCost = estimateRemainingCost(&startStep, maxSplices, options.costModel, &heuristic);
+            searchIterativeDeepening(
+                startStep,
+                startRemainingCost,
+                &|step| makeEstimatedSuccessors(step, maxSplices, options, deadEnds, &heuristic),
+                &|step| isGoalReached(step, &Goal::Target(&level.target), options.matching),
+                options.transpositionTableCapacity,
+                progress)
+        }
+    };
+    path.map(|path| rebuildPath(path, maxSplices, options))
+}
+
+// Expands the state with the lowest estimated total cost first, preferring states further from the start on ties.
+// Without an estimate of the remaining cost it behaves like Dijkstra's algorithm. With more threads, a batch of states
+// sharing the best estimate and distance is expanded at once. Their order does not matter for optimality, because
+// none of them can improve another one.
+fn searchBestFirst(
+    startStep: SolutionStep,
+    startRemainingCost: Cost,
+    makeSuccessors: impl Fn(&SolutionStep) -> Vec<EstimatedStep> + Sync,
+    isGoalReached: impl Fn(&SolutionStep) -> bool,
+    threadCount: usize,
+    progress: &mut SearchProgress)
+    -> Option<Vec<SolutionStep>>
+{
+    let mut frontier = BinaryHeap::from([(Reverse(startRemainingCost), 0, 0)]);
+    let mut nodeIndices = HashMap::from([(startStep.stateKey(), 0)]);
+    progress.recordGeneratedStep(&startStep);
+    let mut nodes = vec![SearchNode{step: startStep, parentIndexOpt: None, cost: 0, remainingCost: startRemainingCost}];
+    let maxBatchSize = match threadCount {
+        1 => 1,
+        _ => threadCount * STATES_PER_THREAD
+    };
+    while let Some(batch) = popBatch(&mut frontier, &nodes, maxBatchSize) {
+        if progress.isInterrupted() {
+            return None;
+        }
+        if let Some(goalIndex) = batch.iter().copied().find(|nodeIndex| isGoalReached(&nodes[*nodeIndex].step)) {
+            return Some(collectPath(&nodes, goalIndex));
+        }
+
+        let batchSteps = batch.iter().map(|nodeIndex| &nodes[*nodeIndex].step).collect_vec();
+        let batchSuccessors = expandSteps(&batchSteps, &makeSuccessors, threadCount);
+        for (nodeIndex, successors) in batch.into_iter().zip(batchSuccessors) {
+            progress.recordExpandedStep(&nodes[nodeIndex].step);
+            let cost = nodes[nodeIndex].cost;
+            for (successor, actionCost, remainingCost) in successors {
+                progress.recordGeneratedStep(&successor);
+                let successorCost = cost + actionCost;
+                let successorIndex = match nodeIndices.entry(successor.stateKey()) {
+                    Entry::Vacant(entry) => {
+                        let successorIndex = nodes.len();
+                        nodes.push(SearchNode{
+                            step: successor, parentIndexOpt: Some(nodeIndex), cost: successorCost, remainingCost});
+                        entry.insert(successorIndex);
+                        successorIndex
+                    },
+                    Entry::Occupied(entry) => {
+                        progress.statistics.duplicateHits += 1;
+                        let successorIndex = *entry.get();
+                        let successorNode = &mut nodes[successorIndex];
+                        if successorCost >= successorNode.cost {
+                            continue;
+                        }
+                        successorNode.parentIndexOpt = Some(nodeIndex);
+                        successorNode.cost = successorCost;
+                        successorIndex
+                    }
+                };
+                let estimatedTotalCost = successorCost + nodes[successorIndex].remainingCost;
+                frontier.push((Reverse(estimatedTotalCost), successorCost, successorIndex));
+            }
+        }
+        progress.statistics.peakFrontierSize = progress.statistics.peakFrontierSize.max(frontier.len());
+    }
+    None
+}
+
+// Pops states with the same estimated total cost and distance from the start, skipping outdated entries.
+fn popBatch(frontier: &mut BinaryHeap<FrontierEntry>, nodes: &[SearchNode], maxBatchSize: usize) -> Option<Vec<NodeIndex>>
+{
+    let (estimatedTotalCost, cost, _nodeIndex) = *frontier.peek()?;
+    let mut batch = vec![];
+    while batch.len() < maxBatchSize
+        && frontier.peek().is_some_and(|entry| entry.0 == estimatedTotalCost && entry.1 == cost)
+    {
+        let (_, _, nodeIndex) = frontier.pop().unwrap();
+        if cost == nodes[nodeIndex].cost {
+            batch.push(nodeIndex);
+        }
+    }
+    Some(batch)
+}
+
+// Starting threads takes longer than expanding a few states, so small batches are expanded on the calling thread.
+fn expandSteps(
+    steps: &[&SolutionStep], makeSuccessors: &(impl Fn(&SolutionStep) -> Vec<EstimatedStep> + Sync), threadCount: usize)
+    -> Vec<Vec<EstimatedStep>>
+{
+    if steps.len() <= STATES_PER_THREAD {
+        return steps.iter().map(|step| makeSuccessors(step)).collect();
+    }
+
+    let chunkSize = steps.len().div_ceil(threadCount);
+    std::thread::scope(|scope| {
+        let workers = steps.chunks(chunkSize)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|step| makeSuccessors(step)).collect_vec()))
+            .collect_vec();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    })
+}
+
+// Repeats a depth-first search bounded by the estimated total cost, raising the bound to the lowest estimate exceeding
+// it each time, so only the current path and the transposition table are kept in memory. The table remembers the
+// lowest cost each state was reached with during an iteration, so a state reached again without improvement is not
+// expanded twice. When the table is full, new states are expanded without being remembered.
+fn searchIterativeDeepening(
+    startStep: SolutionStep,
+    startRemainingCost: Cost,
+    makeSuccessors: &dyn Fn(&SolutionStep) -> Vec<EstimatedStep>,
+    isGoalReached: &dyn Fn(&SolutionStep) -> bool,
+    transpositionTableCapacity: usize,
+    progress: &mut SearchProgress)
+    -> Option<Vec<SolutionStep>>
+{
+    progress.recordGeneratedStep(&startStep);
+    let mut bound = startRemainingCost;
+    let mut search = DepthFirstSearch{
+        makeSuccessors,
+        isGoalReached,
+        transpositionTable: HashMap::new(),
+        transpositionTableCapacity,
+        path: vec![startStep],
+        progress};
+    loop {
+        search.transpositionTable.clear();
+        match search.searchFromLastStep(0, startRemainingCost, bound) {
+            DepthFirstOutcome::Found => return Some(search.path),
+            DepthFirstOutcome::NotFound{nextBoundOpt: Some(nextBound)} => bound = nextBound,
+            DepthFirstOutcome::NotFound{nextBoundOpt: None} | DepthFirstOutcome::Interrupted => return None
+        }
+    }
+}
+
+struct DepthFirstSearch<'a, 'b>
+{
+    makeSuccessors: &'a dyn Fn(&SolutionStep) -> Vec<EstimatedStep>,
+    isGoalReached: &'a dyn Fn(&SolutionStep) -> bool,
+    transpositionTable: HashMap<StateKey, Cost>,
+    transpositionTableCapacity: usize,
+    path: Vec<SolutionStep>,
+    progress: &'a mut SearchProgress<'b>
+}
+
+impl<'a, 'b> DepthFirstSearch<'a, 'b>
+{
+    fn searchFromLastStep(&mut self, cost: Cost, remainingCost: Cost, bound: Cost) -> DepthFirstOutcome
+    {
+        let step = self.path.last().unwrap();
+        let estimatedTotalCost = cost + remainingCost;
+        if estimatedTotalCost > bound {
+            return DepthFirstOutcome::NotFound{nextBoundOpt: Some(estimatedTotalCost)};
+        }
+        if (self.isGoalReached)(step) {
+            return DepthFirstOutcome::Found;
+        }
+        if self.progress.isInterrupted() {
+            return DepthFirstOutcome::Interrupted;
+        }
+        if !self.recordVisit(cost) {
+            return DepthFirstOutcome::NotFound{nextBoundOpt: None};
+        }
+
+        let step = self.path.last().unwrap();
+        self.progress.recordExpandedStep(step);
+        let mut nextBoundOpt = None;
+        for (successor, actionCost, successorRemainingCost) in (self.makeSuccessors)(step) {
+            self.progress.recordGeneratedStep(&successor);
+            self.path.push(successor);
+            self.progress.statistics.peakFrontierSize = self.progress.statistics.peakFrontierSize.max(self.path.len());
+            match self.searchFromLastStep(cost + actionCost, successorRemainingCost, bound) {
+                DepthFirstOutcome::NotFound{nextBoundOpt: successorNextBoundOpt} => {
+                    nextBoundOpt = nextBoundOpt.into_iter().chain(successorNextBoundOpt).min();
+                },
+                outcome => return outcome
+            }
+            self.path.pop();
+        }
+        DepthFirstOutcome::NotFound{nextBoundOpt}
+    }
+
+    // Returns false if the last step of the path was already expanded with the same or a lower cost.
+    fn recordVisit(&mut self, cost: Cost) -> bool
+    {
+        let stateKey = self.path.last().unwrap().stateKey();
+        match self.transpositionTable.get_mut(&stateKey) {
+            Some(bestCost) if *bestCost <= cost => {
+                self.progress.statistics.duplicateHits += 1;
+                false
+            },
+            Some(bestCost) => {
+                *bestCost = cost;
+                true
+            },
+            None => {
+                if self.transpositionTable.len() < self.transpositionTableCapacity {
+                    self.transpositionTable.insert(stateKey, cost);
+                }
+                true
+            }
+        }
+    }
+}
+
+enum DepthFirstOutcome
+{
+    Found,
+    NotFound{nextBoundOpt: Option<Cost>},
+    Interrupted
+}
+
+// The shapes the target can be reached from with a few splices are found by a breadth-first search backward from
+// the target. This gives exact remaining costs for strands without special cells, which lets the forward search stop
+// as soon as it meets them. Strands with special cells still need mutations, so only the splices after the last
+// mutation are searched backward. Splices work the same on strands differing only in the order of children, so
+// when the order does not matter, shapes which ignore it are searched.
+struct TargetPerimeter
+{
+    nodes: Vec<PerimeterNode>,
+    nodeIndices: HashMap<CanonicalShape, NodeIndex>,
+    matching: SurfaceMatching,
+    depth: SpliceCount,
+    isComplete: bool
+}
+
+struct PerimeterNode
+{
+    distance: SpliceCount,
+    nextIndexOpt: Option<NodeIndex>
+}
+
+impl TargetPerimeter
+{
+    fn new(target: &Strand, maxDepth: SpliceCount, options: &SolverOptions, progress: &mut SearchProgress)
+        -> Option<Self>
+    {
+        let targetShape = target.surfaceShape(options.matching);
+        // Strands with the special cells of the target might reach it sooner by mutating, so the splices leading
+        // to it are not necessarily the best way and only the target itself can be met.
+        let maxDepth = if targetShape.hasSpecialCells() { 0 } else { maxDepth };
+        let mut newSelf = Self{
+            nodes: vec![PerimeterNode{distance: 0, nextIndexOpt: None}],
+            nodeIndices: HashMap::from([(targetShape, 0)]),
+            matching: options.matching,
+            depth: 0,
+            isComplete: false};
+        let mut layer = vec![(target.clone(), 0)];
+        while newSelf.depth < maxDepth && newSelf.nodes.len() < MAX_PERIMETER_SIZE {
+            let mut nextLayer = vec![];
+            for (strand, nodeIndex) in layer {
+                if progress.isInterrupted() {
+                    return None;
+                }
+                let origins = makeSpliceOrigins(&strand, &options.rules);
+                progress.recordBackwardExpansion(origins.len());
+                for splicedStrand in origins {
+                    match newSelf.nodeIndices.entry(splicedStrand.surfaceShape(options.matching)) {
+                        Entry::Vacant(entry) => {
+                            let splicedIndex = newSelf.nodes.len();
+                            newSelf.nodes.push(
+                                PerimeterNode{distance: newSelf.depth + 1, nextIndexOpt: Some(nodeIndex)});
+                            entry.insert(splicedIndex);
+                            nextLayer.push((splicedStrand, splicedIndex));
+                        },
+                        Entry::Occupied(_) => progress.statistics.duplicateHits += 1
+                    }
+                }
+            }
+            newSelf.depth += 1;
+            if nextLayer.is_empty() {
+                newSelf.isComplete = true;
+                break;
+            }
+            layer = nextLayer;
+        }
+        Some(newSelf)
+    }
+
+    // Returns None when the target cannot be reached within the remaining splices.
+    fn estimateRemainingActions(&self, step: &SolutionStep, maxSplices: SpliceCount, heuristic: &StrandHeuristic)
+        -> Option<ActionCounts>
+    {
+        let remainingSplices = maxSplices - step.spliceCount;
+        let actions = heuristic.estimateRemainingActions(&step.strand, remainingSplices)?;
+        if step.shape.hasSpecialCells() {
+            return Some(actions);
+        }
+        let splices = self.findMinRemainingSplices(step)?.max(actions.splices);
+        (splices <= remainingSplices.into()).then_some(ActionCounts{splices, ..ActionCounts::default()})
+    }
+
+    fn findMinRemainingSplices(&self, step: &SolutionStep) -> Option<usize>
+    {
+        match self.findNodeIndex(step) {
+            Some(nodeIndex) => Some(self.nodes[nodeIndex].distance.into()),
+            None if self.isComplete => None,
+            None => Some(usize::from(self.depth) + 1)
+        }
+    }
+
+    fn isWithinReach(&self, step: &SolutionStep, maxSplices: SpliceCount) -> bool
+    {
+        self.findNodeIndex(step).is_some_and(|nodeIndex| self.nodes[nodeIndex].distance <= maxSplices - step.spliceCount)
+    }
+
+    fn findNodeIndex(&self, step: &SolutionStep) -> Option<NodeIndex>
+    {
+        match self.matching {
+            SurfaceMatching::Ordered => self.nodeIndices.get(&step.shape).copied(),
+            SurfaceMatching::Unordered => self.nodeIndices.get(&step.strand.unorderedCanonicalShape()).copied()
+        }
+    }
+
+    // Appends the splices leading from the last step of a forward path to the target.
+    fn extendPath(&self, mut path: Vec<SolutionStep>, maxSplices: SpliceCount, options: &SolverOptions) -> Vec<SolutionStep>
+    {
+        let mut nodeIndex = self.findNodeIndex(path.last().unwrap()).unwrap();
+        while let Some(nextIndex) = self.nodes[nodeIndex].nextIndexOpt {
+            let (nextStep, _cost) = makeSuccessors(path.last().unwrap(), maxSplices, options).into_iter()
+                .find(|(step, _cost)| self.findNodeIndex(step) == Some(nextIndex))
+                .unwrap();
+            path.push(nextStep);
+            nodeIndex = nextIndex;
+        }
+        path
+    }
+}
+
+fn collectPath(nodes: &[SearchNode], lastNodeIndex: NodeIndex) -> Vec<SolutionStep>
+{
+    let mut path = vec![];
+    let mut nodeIndexOpt = Some(lastNodeIndex);
+    while let Some(nodeIndex) = nodeIndexOpt {
+        path.push(nodes[nodeIndex].step.clone());
+        nodeIndexOpt = nodes[nodeIndex].parentIndexOpt;
+    }
+    path.reverse();
+    path
+}
+
+struct SearchNode
+{
+    step: SolutionStep,
+    parentIndexOpt: Option<NodeIndex>,
+    cost: Cost,
+    // The estimate of the cost left to the goal, which depends only on the step.
+    remainingCost: Cost
+}
+
+type NodeIndex = usize;
+
+// Ordered so that the heap pops the lowest estimated total cost first and then the highest cost from the start.
+type FrontierEntry = (Reverse<Cost>, Cost, NodeIndex);
+
+fn findPaths(
+    level: &Level, maxSplices: SpliceCount, options: &SolverOptions, selection: SolutionSelection,
+    deadEnds: &DeadEndDetector, progress: &mut SearchProgress)
+    -> Vec<Vec<SolutionStep>>
+{
+    let startStep = SolutionStep::new(level.start.clone(), NO_LAST_ACTION, START_SPLICE_COUNT);
+    let heuristic = StrandHeuristic::new(&level.target, options.rules.swapRule, options.matching);
+    let successors = |step: &SolutionStep| {
+        if progress.isInterrupted() {
+            return vec![];
+        }
+        progress.recordExpandedStep(step);
+        match options.algorithm {
+            SearchAlgorithm::Dijkstra => makeLiveSuccessors(step, maxSplices, options, deadEnds),
+            SearchAlgorithm::AStar | SearchAlgorithm::IdaStar | SearchAlgorithm::Bidirectional =>
+                makeReachableSuccessors(step, maxSplices, options, deadEnds, &heuristic)
+        }
+    };
+    let goal = Goal::Target(&level.target);
+    let isGoal = |step: &SolutionStep| isGoalReached(step, &goal, options.matching);
+    let paths = match selection {
+        // Paths come ordered by their cost, whose primary part outweighs the tie breaker, so the optimal paths
+        // are the first ones until the primary cost grows.
+        SolutionSelection::AllOptimal{maxCount} => {
+            let paths = yen(&startStep, successors, isGoal, maxCount);
+            let optimalCostOpt = paths.first().map(|(_path, cost)| primaryCost(*cost));
+            paths.into_iter()
+                .take_while(|(_path, cost)| Some(primaryCost(*cost)) == optimalCostOpt)
+                .map(|(path, _cost)| path)
+                .collect_vec()
+        },
+        SolutionSelection::Best{count} => {
+            yen(&startStep, successors, isGoal, count).into_iter().map(|(path, _cost)| path).collect_vec()
+        }
+    };
+    paths.into_iter().map(|path| rebuildPath(path, maxSplices, options)).collect()
+}
+
+// Search states are deduplicated by their shape, so a step on the found path may have been recorded when it was
+// reached from a different, but equally shaped, predecessor. Replaying the path makes node ids in actions consistent.
+fn rebuildPath(path: Vec<SolutionStep>, maxSplices: SpliceCount, options: &SolverOptions) -> Vec<SolutionStep>
+{
+    let mut pathIter = path.into_iter();
+    let mut rebuiltPath = vec![pathIter.next().unwrap()];
+    for nextStep in pathIter {
+        let successors = makeSuccessors(rebuiltPath.last().unwrap(), maxSplices, options);
+        let (successor, _cost) = successors.into_iter().find(|(successor, _cost)| *successor == nextStep).unwrap();
+        rebuiltPath.push(successor);
+    }
+    rebuiltPath
+}
+
+fn makeReachableSuccessors(
+    solutionStep: &SolutionStep, maxSplices: SpliceCount, options: &SolverOptions, deadEnds: &DeadEndDetector,
+    heuristic: &StrandHeuristic)
+    -> Vec<StepAndCost>
+{
+    let mut successors = makeLiveSuccessors(solutionStep, maxSplices, options, deadEnds);
+    successors.retain(|(step, _cost)| isTargetReachable(step, maxSplices, heuristic));
+    successors
+}
+
+// The remaining cost of each successor is estimated once, when it is generated.
+fn makeEstimatedSuccessors(
+    solutionStep: &SolutionStep, maxSplices: SpliceCount, options: &SolverOptions, deadEnds: &DeadEndDetector,
+    heuristic: &StrandHeuristic)
+    -> Vec<EstimatedStep>
+{
+    estimateSuccessors(
+        makeLiveSuccessors(solutionStep, maxSplices, options, deadEnds),
+        |step| heuristic.estimateRemainingActions(&step.strand, maxSplices - step.spliceCount),
+        options.costModel)
+}
+
+// Successors without an estimate cannot reach the target and are left out.
+fn estimateSuccessors(
+    successors: Vec<StepAndCost>, estimateRemainingActions: impl Fn(&SolutionStep) -> Option<ActionCounts>,
+    costModel: CostModel)
+    -> Vec<EstimatedStep>
+{
+    successors.into_iter()
+        .filter_map(|(step, cost)| {
+            let remainingActions = estimateRemainingActions(&step)?;
+            Some((step, cost, costModel.cost(remainingActions)))
+        })
+        .collect()
+}
+
+fn withoutEstimates(successors: Vec<StepAndCost>) -> Vec<EstimatedStep>
+{
+    successors.into_iter().map(|(step, cost)| (step, cost, 0)).collect()
+}
+
+// Splices keep the cells of a strand, so only a mutation can turn a strand which is not a dead end into one.
+fn makeLiveSuccessors(
+    solutionStep: &SolutionStep, maxSplices: SpliceCount, options: &SolverOptions, deadEnds: &DeadEndDetector)
+    -> Vec<StepAndCost>
+{
+    let mut successors = makeSuccessors(solutionStep, maxSplices, options);
+    successors.retain(|(step, _cost)| {
+        !matches!(step.lastAction, Some(Action::Mutate{..})) || !deadEnds.isDeadEnd(&step.strand)
+    });
+    successors
+}
+
+fn isTargetReachable(solutionStep: &SolutionStep, maxSplices: SpliceCount, heuristic: &StrandHeuristic) -> bool
+{
+    heuristic.estimateRemainingActions(&solutionStep.strand, maxSplices - solutionStep.spliceCount).is_some()
+}
+
+fn estimateRemainingCost(
+    solutionStep: &SolutionStep, maxSplices: SpliceCount, costModel: CostModel, heuristic: &StrandHeuristic) -> Cost
+{
+    // Unreachable states are filtered out of the successors, but the start state is never filtered.
+    let remainingActions = heuristic.estimateRemainingActions(&solutionStep.strand, maxSplices - solutionStep.spliceCount);
+    costModel.cost(remainingActions.unwrap_or_default())
+}
+
+pub(crate) fn makeSuccessors(solutionStep: &SolutionStep, maxSplices: SpliceCount, options: &SolverOptions)
+    -> Vec<StepAndCost>
+{
+    let rules = &options.rules;
     let mut successors = vec![];
-    let strand = &solutionStep.strand;
-    let mut dfs = Dfs::new(strand, Strand::root());
-    while let Some(strandNodeId) = dfs.next(strand) {
-        successors.extend(makeSolutionStepsBySplicing(strandNodeId, solutionStep, maxSplices));
+    let canSplice = solutionStep.spliceCount < maxSplices;
+    if canSplice || rules.swapRule == SwapRule::Free {
+        for (strand, action) in makeSplices(&solutionStep.strand, rules) {
+            let usedSplices = rules.countActions(&action).splices.try_to::<SpliceCount>().unwrap();
+            if canSplice || usedSplices == 0 {
+                successors.push(SolutionStep::new(strand, Some(action), solutionStep.spliceCount + usedSplices));
+            }
+        }
     }
 
-    if let Some(newSolutionStep) = makeSolutionStepByMutation(solutionStep) {
+    if let Some(newSolutionStep) = makeSolutionStepByMutation(solutionStep, rules, options.limits.maxNodeCount) {
         successors.push(newSolutionStep);
     }
 
-    successors.into_iter().map(|node| (node, 1)).collect()
+    successors.into_iter().map(|step| {
+        let cost = options.costModel.cost(rules.countActions(step.lastAction.as_ref().unwrap()));
+        (step, cost)
+    }).collect()
 }
 
-fn makeSolutionStepsBySplicing(nodeId: NodeId, solutionStep: &SolutionStep, maxSplices: SpliceCount) -> Vec<SolutionStep>
+fn makeSplices(strand: &Strand, rules: &RuleSet) -> Vec<(Strand, Action)>
+{
+    let mut splices = vec![];
+    let mut dfs = Dfs::new(strand, Strand::root());
+    while let Some(strandNodeId) = dfs.next(strand) {
+        splices.extend(makeSplicesOfNode(strandNodeId, strand, rules));
+    }
+    splices
+}
+
+fn makeSplicesOfNode(nodeId: NodeId, strand: &Strand, rules: &RuleSet) -> Vec<(Strand, Action)>
 {
-    let strand = &solutionStep.strand;
     let parent = match strand.parentId(nodeId) {
         Some(parent) => parent,
         None => return vec![]
     };
 
     let mut result = vec![];
-    if solutionStep.spliceCount < maxSplices {
-        let newSpliceCount = solutionStep.spliceCount + 1;
-        let newParents = findPotentialNewParents(nodeId, parent, strand);
-        for newParent in newParents {
-            result.push(SolutionStep::new(
-                makeStrandWithNewParent(nodeId, newParent, strand),
-                Some(Action::ChangeParent { node: nodeId, oldParent: parent, newParent }),
-                newSpliceCount));
+    let newParents = findPotentialNewParents(nodeId, parent, strand, rules);
+    for (newParent, slot) in newParents {
+        result.push((
+            makeStrandWithNewParent(nodeId, newParent, slot, strand),
+            Action::ChangeParent { node: nodeId, oldParent: parent, newParent, slot }));
+    }
+    if strand.childCount(parent) == 2 {
+        result.push((makeStrandWithSwappedChildren(parent, strand), Action::SwapChildren { parent }));
+    }
+    result
+}
+
+// Returns the strands which the given strand can be made from by a single splice.
+fn makeSpliceOrigins(strand: &Strand, rules: &RuleSet) -> Vec<Strand>
+{
+    let mut origins = vec![];
+    for nodeId in strand.collectNodeIds() {
+        let parentId = match strand.parentId(nodeId) {
+            Some(parentId) => parentId,
+            None => continue
+        };
+        if strand.childIds(parentId).len() == 2 {
+            origins.push(makeStrandWithSwappedChildren(parentId, strand));
         }
-        if strand.childCount(parent) == 2 {
-            result.push(SolutionStep::new(
-                makeStrandWithSwappedChildren(parent, strand),
-                Some(Action::SwapChildren { parent }),
-                newSpliceCount));
+        let mut excludedIds = vec![nodeId, parentId];
+        excludedIds.extend(findChildrenRecursively(nodeId, strand));
+        for oldParentId in strand.collectNodeIds() {
+            if excludedIds.contains(&oldParentId) {
+                continue;
+            }
+            let slots = match strand.childCount(oldParentId) {
+                0 => &[ChildSlot::Right][..],
+                1 => &[ChildSlot::Right, ChildSlot::Left][..],
+                _ => &[]
+            };
+            for slot in slots {
+                let origin = makeStrandWithNewParent(nodeId, oldParentId, *slot, strand);
+                if rules.canJoin(nodeId, parentId, &origin) {
+                    origins.push(origin);
+                }
+            }
         }
     }
-
-    result
+    origins
 }
 
-fn findPotentialNewParents(nodeId: NodeId, parentId: NodeId, strand: &Strand) -> Vec<NodeId>
+fn findPotentialNewParents(nodeId: NodeId, parentId: NodeId, strand: &Strand, rules: &RuleSet) -> Vec<(NodeId, ChildSlot)>
 {
-    let mut outputNodeIds = strand.collectNodeIds();
     let mut excludedIndices = vec![nodeId, parentId];
     excludedIndices.extend(findChildrenRecursively(nodeId, strand));
 
-    outputNodeIds = outputNodeIds.into_iter()
-        .filter(|id| !excludedIndices.contains(id))
-        .filter(|id| match strand.childIds(*id) {
-            [] => true,
-            [childId] => strand.cellKind(*childId) != CellKind::Doubler && strand.cellKind(nodeId) != CellKind::Doubler,
-            [_, _] => false,
-            _ => panic!("Cell can't have more than two children")
-        }).collect();
-    outputNodeIds
+    strand.collectNodeIds().into_iter()
+        .filter(|id| !excludedIndices.contains(id) && rules.canJoin(nodeId, *id, strand))
+        .flat_map(|id| match strand.childCount(id) {
+            0 => vec![(id, ChildSlot::Right)],
+            _ => vec![(id, ChildSlot::Right), (id, ChildSlot::Left)]
+        }).collect()
 }
 
-fn findChildrenRecursively(nodeId: NodeId, strand: &Strand) -> Vec<NodeId>
+pub(crate) fn findChildrenRecursively(nodeId: NodeId, strand: &Strand) -> Vec<NodeId>
 {
     let mut result = vec![];
     let mut dfs = Dfs::new(strand, nodeId);
@@ -90,10 +784,10 @@ fn findChildrenRecursively(nodeId: NodeId, strand: &Strand) -> Vec<NodeId>
     result
 }
 
-fn makeStrandWithNewParent(nodeId: NodeId, newParentId: NodeId, strand: &Strand) -> Strand
+fn makeStrandWithNewParent(nodeId: NodeId, newParentId: NodeId, slot: ChildSlot, strand: &Strand) -> Strand
 {
     let mut newStrand = strand.clone();
-    newStrand.changeParent(nodeId, newParentId);
+    newStrand.changeParent(nodeId, newParentId, slot);
     newStrand
 }
 
@@ -104,45 +798,623 @@ fn makeStrandWithSwappedChildren(parentId: NodeId, strand: &Strand) -> Strand
     newStrand
 }
 
-fn makeSolutionStepByMutation(solutionStep: &SolutionStep) -> Option<SolutionStep>
+fn makeSolutionStepByMutation(solutionStep: &SolutionStep, rules: &RuleSet, maxNodeCount: usize) -> Option<SolutionStep>
 {
+    let cellIds = rules.findCellsToMutate(&solutionStep.strand);
+    if cellIds.is_empty() || isNodeCountExceededByMutation(&solutionStep.strand, &cellIds, maxNodeCount) {
+        return None;
+    }
     let mut newStrand = solutionStep.strand.clone();
-    let mutatedNodeIds = newStrand.mutate();
-    if !mutatedNodeIds.is_empty() {
-        Some(SolutionStep::new(newStrand, Some(Action::Mutate{nodes: mutatedNodeIds}), solutionStep.spliceCount))
-    } else {
-        None
+    newStrand.mutate(&cellIds);
+    Some(SolutionStep::new(newStrand, Some(Action::Mutate{nodes: cellIds}), solutionStep.spliceCount))
+}
+
+pub(crate) fn isNodeCountExceededByMutation(strand: &Strand, cellIds: &[NodeId], maxNodeCount: usize) -> bool
+{
+    strand.nodeCountAfterMutation(cellIds) > maxNodeCount.min(MAX_NODE_COUNT)
+}
+
+fn isGoalReached(node: &SolutionStep, goal: &Goal, matching: SurfaceMatching) -> bool
+{
+    match goal {
+        Goal::Target(target) => node.strand.matchesSurface(target, matching),
+        Goal::Pattern(pattern) => pattern.matches(&node.strand, matching)
     }
 }
 
-fn isGoalReached(node: &SolutionStep, target: &Strand) -> bool
+enum Goal<'a>
 {
-    node.strand.isEqualOnSurface(target)
+    Target(&'a Strand),
+    Pattern(&'a StrandPattern)
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug)]
+pub(crate) struct SolverOptions
+{
+    pub algorithm: SearchAlgorithm,
+    pub costModel: CostModel,
+    pub limits: SearchLimits,
+    pub cancellationToken: CancellationToken,
+    // Only the search for a single solution uses more than one thread.
+    pub threadCount: usize,
+    pub transpositionTableCapacity: usize,
+    pub rules: RuleSet,
+    pub matching: SurfaceMatching,
+    // Turning it off shows what pruning dead ends saves, it never changes the cost of the solution found.
+    pub pruneDeadEnds: bool
+}
+
+impl Default for SolverOptions
+{
+    fn default() -> Self
+    {
+        Self{
+            algorithm: SearchAlgorithm::default(),
+            costModel: CostModel::default(),
+            limits: SearchLimits::default(),
+            cancellationToken: CancellationToken::default(),
+            threadCount: 1,
+            transpositionTableCapacity: DEFAULT_TRANSPOSITION_TABLE_CAPACITY,
+            rules: RuleSet::default(),
+            matching: SurfaceMatching::default(),
+            pruneDeadEnds: true}
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum SearchAlgorithm
+{
+    Dijkstra,
+    #[default]
+    AStar,
+    // Enumerating solutions falls back to A* for the modes below.
+    IdaStar,
+    Bidirectional
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum SolutionSelection
+{
+    AllOptimal{maxCount: usize},
+    Best{count: usize}
+}
+
+// Describes a failed search, which helps to tell apart a level with a mistake in its definition from a level
+// that is impossible to solve.
+#[derive(Debug)]
+pub(crate) struct NoSolution
+{
+    pub statesExplored: usize,
+    pub searchEnd: SearchEnd,
+    pub closestStrand: Strand,
+    // None when searching for a pattern, which has no distance to measure.
+    pub closestSurfaceDistanceOpt: Option<usize>
+}
+
+impl Display for NoSolution
+{
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        write!(formatter, "No solution was found after exploring {} states", self.statesExplored)?;
+        match self.searchEnd {
+            SearchEnd::Exhausted => write!(formatter, ", the whole search space was explored, so the level cannot be solved.")?,
+            SearchEnd::LimitReached(limit) => write!(formatter, ", the search was cut short by the {}.", limit)?,
+            SearchEnd::Cancelled => write!(formatter, ", the search was cancelled.")?
+        }
+        match self.closestSurfaceDistanceOpt {
+            Some(distance) => {
+                write!(formatter, " The closest strand reached differs from the target in {} cells.", distance)
+            },
+            None => Ok(())
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SearchStatistics
+{
+    pub statesGenerated: usize,
+    pub statesExpanded: usize,
+    pub duplicateHits: usize,
+    // Iterative deepening keeps no frontier, so for it this is the length of the longest path searched.
+    pub peakFrontierSize: usize,
+    pub maxStrandSize: usize,
+    pub spliceSuccessors: usize,
+    pub mutationSuccessors: usize,
+    // Successors left out because their node count or special cells can no longer become the target's.
+    pub deadEndsByNodeCount: usize,
+    pub deadEndsByCellKinds: usize,
+    pub bidirectionalFallbackOpt: Option<BidirectionalFallback>,
+    pub wallTime: Duration
+}
+
+// Why the bidirectional search had to search forward only, like A*.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum BidirectionalFallback
+{
+    FreeSwaps,
+    SpecialCellsInTarget
+}
+
+impl Display for BidirectionalFallback
+{
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            BidirectionalFallback::FreeSwaps =>
+                write!(formatter, "Swaps are free, so A* was used instead of the bidirectional search."),
+            BidirectionalFallback::SpecialCellsInTarget =>
+                write!(formatter, "The target has special cells, so only a forward search like A* was used.")
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum SearchEnd
+{
+    Exhausted,
+    LimitReached(SearchLimit),
+    Cancelled
+}
+
+struct SearchProgress<'a>
+{
+    targetOpt: Option<&'a Strand>,
+    options: &'a SolverOptions,
+    startTime: Instant,
+    interruptionOpt: Option<SearchEnd>,
+    isNodeCountLimitReached: bool,
+    statistics: SearchStatistics,
+    closestStrand: Strand,
+    closestSurfaceDistanceOpt: Option<usize>
+}
+
+impl<'a> SearchProgress<'a>
+{
+    fn new(start: &Strand, targetOpt: Option<&'a Strand>, options: &'a SolverOptions) -> Self
+    {
+        Self{
+            targetOpt,
+            options,
+            startTime: Instant::now(),
+            interruptionOpt: None,
+            isNodeCountLimitReached: false,
+            statistics: SearchStatistics::default(),
+            closestStrand: start.clone(),
+            closestSurfaceDistanceOpt: targetOpt.map(|target| start.surfaceDistance(target))}
+    }
+
+    fn recordGeneratedStep(&mut self, step: &SolutionStep)
+    {
+        self.statistics.statesGenerated += 1;
+        self.statistics.maxStrandSize = self.statistics.maxStrandSize.max(step.shape.cellCount());
+        match step.lastAction {
+            Some(Action::ChangeParent{..} | Action::SwapChildren{..}) => self.statistics.spliceSuccessors += 1,
+            Some(Action::Mutate{..}) => self.statistics.mutationSuccessors += 1,
+            None => ()
+        }
+    }
+
+    fn isInterrupted(&mut self) -> bool
+    {
+        if self.interruptionOpt.is_none() {
+            let limits = &self.options.limits;
+            if self.options.cancellationToken.isCancelled() {
+                self.interruptionOpt = Some(SearchEnd::Cancelled);
+            } else if limits.timeLimitOpt.is_some_and(|timeLimit| self.startTime.elapsed() > timeLimit) {
+                self.interruptionOpt = Some(SearchEnd::LimitReached(SearchLimit::Time));
+            } else if limits.maxExpandedStatesOpt.is_some_and(|maxStates| self.statistics.statesExpanded >= maxStates) {
+                self.interruptionOpt = Some(SearchEnd::LimitReached(SearchLimit::ExpandedStates));
+            }
+        }
+        self.interruptionOpt.is_some()
+    }
+
+    fn recordBackwardExpansion(&mut self, successorCount: usize)
+    {
+        self.statistics.statesExpanded += 1;
+        self.statistics.statesGenerated += successorCount;
+        self.statistics.spliceSuccessors += successorCount;
+    }
+
+    fn recordDeadEnds(&mut self, deadEnds: &DeadEndDetector)
+    {
+        self.statistics.deadEndsByNodeCount = deadEnds.deadEndsByNodeCount();
+        self.statistics.deadEndsByCellKinds = deadEnds.deadEndsByCellKinds();
+    }
+
+    fn recordExpandedStep(&mut self, step: &SolutionStep)
+    {
+        self.statistics.statesExpanded += 1;
+        let cellIds = self.options.rules.findCellsToMutate(&step.strand);
+        if isNodeCountExceededByMutation(&step.strand, &cellIds, self.options.limits.maxNodeCount) {
+            self.isNodeCountLimitReached = true;
+        }
+        if let (Some(target), Some(closestSurfaceDistance)) = (self.targetOpt, self.closestSurfaceDistanceOpt) {
+            let surfaceDistance = step.strand.surfaceDistance(target);
+            if surfaceDistance < closestSurfaceDistance {
+                self.closestStrand = step.strand.clone();
+                self.closestSurfaceDistanceOpt = Some(surfaceDistance);
+            }
+        }
+    }
+
+    fn intoNoSolution(self) -> NoSolution
+    {
+        let searchEnd = match (self.interruptionOpt, self.isNodeCountLimitReached) {
+            (Some(interruption), _) => interruption,
+            (None, true) => SearchEnd::LimitReached(SearchLimit::NodeCount),
+            (None, false) => SearchEnd::Exhausted
+        };
+        NoSolution{
+            statesExplored: self.statistics.statesExpanded,
+            searchEnd,
+            closestStrand: self.closestStrand,
+            closestSurfaceDistanceOpt: self.closestSurfaceDistanceOpt}
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Solution
+{
+    pub steps: Vec<SolutionStep>,
+    pub spliceCount: SpliceCount,
+    pub mutationCount: usize,
+    pub spliceRating: SpliceRating
+}
+
+impl Solution
+{
+    fn new(steps: Vec<SolutionStep>, optimalSplices: Option<SpliceCount>) -> Self
+    {
+        let spliceCount = steps.last().map_or(START_SPLICE_COUNT, |step| step.spliceCount);
+        let mutationCount = steps.iter().filter(|step| matches!(step.lastAction, Some(Action::Mutate{..}))).count();
+        let spliceRating = match optimalSplices {
+            Some(optimalSplices) if spliceCount <= optimalSplices => SpliceRating::Optimal,
+            _ => SpliceRating::WithinAllowance
+        };
+        Self{steps, spliceCount, mutationCount, spliceRating}
+    }
+
+    pub(crate) fn stepCount(&self) -> usize
+    {
+        self.steps.len().saturating_sub(1)
+    }
+
+    pub(crate) fn actionCounts(&self) -> ActionCounts
+    {
+        let splices = self.spliceCount.into();
+        ActionCounts{splices, mutations: self.mutationCount, freeSwaps: self.stepCount() - splices - self.mutationCount}
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Hint
+{
+    // None when the strand already matches the target.
+    pub nextStepOpt: Option<SolutionStep>,
+    pub remainingSteps: usize,
+    pub remainingSplices: SpliceCount,
+    pub remainingMutations: usize
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum SpliceRating
+{
+    Optimal,
+    WithinAllowance
+}
+
+impl Display for SpliceRating
+{
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            SpliceRating::Optimal => write!(formatter, "optimal"),
+            SpliceRating::WithinAllowance => write!(formatter, "within the allowance")
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub(crate) struct SolutionStep
 {
     pub strand: Strand,
     pub lastAction: Option<Action>,
-    spliceCount: SpliceCount
+    pub spliceCount: SpliceCount,
+    pub shape: CanonicalShape
 }
 
 impl SolutionStep
 {
-    fn new(strand: Strand, lastAction: Option<Action>, spliceCount: SpliceCount) -> Self
+    pub(crate) fn new(strand: Strand, lastAction: Option<Action>, spliceCount: SpliceCount) -> Self
+    {
+        let shape = strand.canonicalShape();
+        Self{strand, lastAction, spliceCount, shape}
+    }
+
+    fn stateKey(&self) -> StateKey
+    {
+        (self.shape.clone(), self.spliceCount)
+    }
+}
+
+// Steps are compared only by what matters for the rest of the search, so the same shape reached through different
+// actions or with differently numbered nodes is a single search state.
+impl PartialEq for SolutionStep
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.shape == other.shape && self.spliceCount == other.spliceCount
+    }
+}
+
+impl Eq for SolutionStep {}
+
+impl Hash for SolutionStep
+{
+    fn hash<H: Hasher>(&self, state: &mut H)
     {
-        Self{strand, lastAction, spliceCount}
+        self.shape.hash(state);
+        self.spliceCount.hash(state);
     }
 }
 
 type StepAndCost = (SolutionStep, Cost);
-type Cost = u8;
+// A step with the cost of its action and the estimated cost left to the goal.
+type EstimatedStep = (SolutionStep, Cost, Cost);
+type StateKey = (CanonicalShape, SpliceCount);
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub(crate) enum Action
 {
-    ChangeParent{node: NodeId, oldParent: NodeId, newParent: NodeId},
+    ChangeParent{node: NodeId, oldParent: NodeId, newParent: NodeId, slot: ChildSlot},
     SwapChildren{parent: NodeId},
     Mutate{nodes: Vec<NodeId>}
 }
+
+impl Display for Action
+{
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            Action::ChangeParent{node, oldParent, newParent, slot} => {
+                write!(formatter, "Change parent of node {} from {} to {}", node, oldParent, newParent)?;
+                match slot {
+                    ChildSlot::Left => write!(formatter, " on the left"),
+                    ChildSlot::Right => Ok(())
+                }
+            },
+            Action::SwapChildren{parent} => {
+                write!(formatter, "Swap children of parent node {}", parent)
+            },
+            Action::Mutate{nodes} => {
+                write!(formatter, "{}", makeMutateStepDescription(nodes))
+            }
+        }
+    }
+}
+
+fn makeMutateStepDescription(nodes: &[NodeId]) -> String
+{
+    match nodes {
+        [] => panic!("Nodes to mutate cannot be empty"),
+        [nodeId] => format!("Mutate node {}", nodeId),
+        [_, ..] => format!("Mutate nodes {}", formatNodesIntoList(nodes))
+    }
+}
+
+fn formatNodesIntoList(nodes: &[NodeId]) -> String
+{
+    let mut output = String::new();
+    for (index, nodeId) in nodes.iter().enumerate() {
+        output.push_str(&format!("{}", nodeId));
+        match nodes.len() - 1 - index {
+            0 => (),
+            1 => output.push_str(" and "),
+            _ => output.push_str(", ")
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::level_maker::{LevelCatalog, SequenceNumber, StrandNumber};
+    use crate::strand_notation::parseStrand;
+
+    #[test]
+    fn allOptimalSolutionsAreDistinctAndEquallyCheap()
+    {
+        let options = SolverOptions{costModel: CostModel::FewestSplices, ..SolverOptions::default()};
+        let primaryCostOf = |solution: &Solution| primaryCost(options.costModel.cost(solution.actionCounts()));
+        let mut worseSolutionCount = 0;
+        for strand in 1..=3 {
+            let (result, _statistics) = solveLevelWithStatistics(makeBuiltInLevel(1, strand), &options);
+            let optimalCost = primaryCostOf(&result.unwrap());
+            let selection = SolutionSelection::AllOptimal{maxCount: 20};
+            let solutions = enumerateSolutions(makeBuiltInLevel(1, strand), &options, selection).unwrap();
+            assert!(!solutions.is_empty());
+            assert!(solutions.iter().all(|solution| primaryCostOf(solution) == optimalCost));
+            let shapePaths = solutions.iter()
+                .map(|solution| solution.steps.iter().map(|step| step.shape.clone()).collect_vec())
+                .collect_vec();
+            assert_eq!(shapePaths.iter().unique().count(), shapePaths.len());
+
+            // The best solutions start with the optimal ones, any others are left out of the selection above.
+            let selection = SolutionSelection::Best{count: 20};
+            let bestSolutions = enumerateSolutions(makeBuiltInLevel(1, strand), &options, selection).unwrap();
+            let (_optimalSolutions, worseSolutions) = bestSolutions.split_at(solutions.len());
+            assert!(worseSolutions.iter().all(|solution| primaryCostOf(solution) > optimalCost));
+            worseSolutionCount += worseSolutions.len();
+        }
+        assert!(worseSolutionCount > 0);
+    }
+
+    #[test]
+    fn impossibleLevelIsExhaustedAndReportsTheClosestStrand()
+    {
+        // Needs two splices, but only one is allowed.
+        let level = Level{maxSplices: 1, optimalSplices: None, ..makeBuiltInLevel(1, 3)};
+        let startDistance = level.start.surfaceDistance(&level.target);
+        let target = level.target.clone();
+        // Dijkstra's algorithm explores every strand within the allowance, instead of leaving out the hopeless ones.
+        let options = SolverOptions{algorithm: SearchAlgorithm::Dijkstra, ..SolverOptions::default()};
+        let (result, statistics) = solveLevelWithStatistics(level, &options);
+        let noSolution = result.unwrap_err();
+        assert_eq!(noSolution.searchEnd, SearchEnd::Exhausted);
+        assert_eq!(noSolution.statesExplored, statistics.statesExpanded);
+        let closestDistance = noSolution.closestSurfaceDistanceOpt.unwrap();
+        assert!(closestDistance < startDistance);
+        assert_eq!(noSolution.closestStrand.surfaceDistance(&target), closestDistance);
+    }
+
+    #[test]
+    fn interruptedSearchesReportWhatStoppedThem()
+    {
+        let limits = SearchLimits{maxExpandedStatesOpt: Some(1), ..SearchLimits::default()};
+        let options = SolverOptions{limits, ..SolverOptions::default()};
+        let (result, _statistics) = solveLevelWithStatistics(makeBuiltInLevel(1, 3), &options);
+        assert_eq!(result.unwrap_err().searchEnd, SearchEnd::LimitReached(SearchLimit::ExpandedStates));
+
+        let options = SolverOptions::default();
+        options.cancellationToken.cancel();
+        let (result, _statistics) = solveLevelWithStatistics(makeBuiltInLevel(1, 3), &options);
+        assert_eq!(result.unwrap_err().searchEnd, SearchEnd::Cancelled);
+
+        // Extending the cell is the only way to the target, but the extended strand would have too many nodes.
+        let makeLevel = || {
+            let start = parseStrand("((E))").unwrap();
+            let mut target = start.clone();
+            target.mutate(&[1]);
+            Level{start, target, maxSplices: 1, optimalSplices: None}
+        };
+        let (result, _statistics) = solveLevelWithStatistics(makeLevel(), &SolverOptions::default());
+        assert!(result.is_ok());
+        let limits = SearchLimits{maxNodeCount: 2, ..SearchLimits::default()};
+        let options = SolverOptions{limits, ..SolverOptions::default()};
+        let (result, _statistics) = solveLevelWithStatistics(makeLevel(), &options);
+        assert_eq!(result.unwrap_err().searchEnd, SearchEnd::LimitReached(SearchLimit::NodeCount));
+    }
+
+    #[test]
+    fn everySearchModeFindsSolutionsAsCheapAsDijkstra()
+    {
+        // Some batches of Dijkstra's algorithm on these levels are large enough to be split between threads.
+        let modes = [
+            (SearchAlgorithm::Dijkstra, 4),
+            (SearchAlgorithm::AStar, 1),
+            (SearchAlgorithm::AStar, 4),
+            (SearchAlgorithm::IdaStar, 1),
+            (SearchAlgorithm::Bidirectional, 1),
+            (SearchAlgorithm::Bidirectional, 4)];
+        for strand in 1..=5 {
+            let options = SolverOptions{algorithm: SearchAlgorithm::Dijkstra, ..SolverOptions::default()};
+            let (result, _statistics) = solveLevelWithStatistics(makeBuiltInLevel(1, strand), &options);
+            let expectedSummary = summarizeSolution(&result.unwrap(), &options);
+            for (algorithm, threadCount) in modes {
+                let options = SolverOptions{algorithm, threadCount, ..SolverOptions::default()};
+                let (result, _statistics) = solveLevelWithStatistics(makeBuiltInLevel(1, strand), &options);
+                let summary = summarizeSolution(&result.unwrap(), &options);
+                assert_eq!(summary, expectedSummary, "1-{} by {:?} on {} threads", strand, algorithm, threadCount);
+            }
+        }
+    }
+
+    #[test]
+    fn bidirectionalSearchReportsFallingBackToAStar()
+    {
+        let options = SolverOptions{algorithm: SearchAlgorithm::Bidirectional, ..SolverOptions::default()};
+        let (_result, statistics) = solveLevelWithStatistics(makeBuiltInLevel(1, 3), &options);
+        assert_eq!(statistics.bidirectionalFallbackOpt, None);
+
+        let rules = RuleSet{swapRule: SwapRule::Free, ..RuleSet::default()};
+        let options = SolverOptions{algorithm: SearchAlgorithm::Bidirectional, rules, ..SolverOptions::default()};
+        let (_result, statistics) = solveLevelWithStatistics(makeBuiltInLevel(1, 3), &options);
+        assert_eq!(statistics.bidirectionalFallbackOpt, Some(BidirectionalFallback::FreeSwaps));
+
+        let start = parseStrand("(()(E))").unwrap();
+        let target = parseStrand("((E)())").unwrap();
+        let level = Level{start, target, maxSplices: 1, optimalSplices: None};
+        let options = SolverOptions{algorithm: SearchAlgorithm::Bidirectional, ..SolverOptions::default()};
+        let (_result, statistics) = solveLevelWithStatistics(level, &options);
+        assert_eq!(statistics.bidirectionalFallbackOpt, Some(BidirectionalFallback::SpecialCellsInTarget));
+    }
+
+    #[test]
+    fn hintContinuesFromAnIntermediateStrandWithTheSplicesLeft()
+    {
+        let options = SolverOptions::default();
+        let Level{target, maxSplices, ..} = makeBuiltInLevel(1, 3);
+        let (result, _statistics) = solveLevelWithStatistics(makeBuiltInLevel(1, 3), &options);
+        let solution = result.unwrap();
+        assert_eq!(solution.spliceCount, 2);
+        let step = &solution.steps[1];
+        assert_eq!(step.spliceCount, 1);
+
+        let hint = findHint(step.strand.clone(), target.clone(), maxSplices, step.spliceCount, &options).unwrap();
+        assert_eq!(hint.remainingSteps, solution.stepCount() - 1);
+        assert_eq!(hint.remainingSplices, 1);
+        let nextStep = hint.nextStepOpt.unwrap();
+        assert_eq!(nextStep.spliceCount, 1);
+        assert_eq!(nextStep.shape, solution.steps[2].shape);
+
+        // With every splice already used, the rest of the solution cannot be followed.
+        let result = findHint(step.strand.clone(), target.clone(), maxSplices, maxSplices, &options);
+        assert_eq!(result.unwrap_err().searchEnd, SearchEnd::Exhausted);
+        let lastStep = solution.steps.last().unwrap();
+        let hint = findHint(lastStep.strand.clone(), target, maxSplices, lastStep.spliceCount, &options).unwrap();
+        assert!(hint.nextStepOpt.is_none());
+        assert_eq!(hint.remainingSteps, 0);
+    }
+
+    #[test]
+    fn spliceIntoTheLeftSlotSavesASwap()
+    {
+        // Moving either subtree of the root under the other puts it on the wrong side, unless it takes the left slot.
+        let level = Level{
+            start: parseStrand("((())(()))").unwrap(),
+            target: parseStrand("(((())()))").unwrap(),
+            maxSplices: 2,
+            optimalSplices: None};
+        let (result, _statistics) = solveLevelWithStatistics(level, &SolverOptions::default());
+        let solution = result.unwrap();
+        assert_eq!(solution.spliceCount, 1);
+        assert!(matches!(solution.steps[1].lastAction, Some(Action::ChangeParent{slot: ChildSlot::Left, ..})));
+    }
+
+    #[test]
+    fn mirrorImageOfTheTargetMatchesOnlyWhenOrderIsIgnored()
+    {
+        let makeLevel = || Level{
+            start: parseStrand("((())(()()))").unwrap(),
+            target: parseStrand("((()())(()))").unwrap(),
+            maxSplices: 1,
+            optimalSplices: None};
+        let (result, _statistics) = solveLevelWithStatistics(makeLevel(), &SolverOptions::default());
+        let solution = result.unwrap();
+        assert_eq!(solution.steps[1].lastAction, Some(Action::SwapChildren{parent: 0}));
+        assert_eq!(solution.stepCount(), 1);
+        let options = SolverOptions{matching: SurfaceMatching::Unordered, ..SolverOptions::default()};
+        let (result, _statistics) = solveLevelWithStatistics(makeLevel(), &options);
+        assert_eq!(result.unwrap().stepCount(), 0);
+
+        // Cell kinds still have to match.
+        let strand = parseStrand("((D)())").unwrap();
+        assert!(strand.matchesSurface(&parseStrand("(()(D))").unwrap(), SurfaceMatching::Unordered));
+        assert!(!strand.matchesSurface(&parseStrand("(()(E))").unwrap(), SurfaceMatching::Unordered));
+    }
+
+    fn makeBuiltInLevel(sequence: u8, strand: u8) -> Level
+    {
+        LevelCatalog::load().unwrap().makeLevel(SequenceNumber(sequence), StrandNumber(strand)).unwrap()
+    }
+
+    fn summarizeSolution(solution: &Solution, options: &SolverOptions) -> (Cost, SpliceCount, usize, usize)
+    {
+        let cost = options.costModel.cost(solution.actionCounts());
+        (cost, solution.spliceCount, solution.mutationCount, solution.stepCount())
+    }
+}
@@ -1,11 +1,24 @@
 #![allow(non_snake_case)]
 
+mod cli;
+mod cost_model;
+mod dead_end_detector;
 mod graph_utils;
 mod gui;
+mod heuristic;
+mod level_file;
+mod level_generator;
 mod level_maker;
 mod level_solver;
+mod rule_set;
+mod search_limits;
+mod solution_verifier;
+mod state_space;
 mod strand;
+mod strand_notation;
+mod strand_pattern;
 
+use crate::cli::runCli;
 use crate::gui::makeGui;
 
 use mimalloc::MiMalloc;
@@ -16,5 +29,12 @@ static ALLOCATOR: MiMalloc = MiMalloc;
 
 fn main()
 {
-    makeGui()
+    let arguments = std::env::args().skip(1).collect::<Vec<_>>();
+    if arguments.is_empty() {
+        return makeGui();
+    }
+    if let Err(error) = runCli(&arguments) {
+        eprintln!("{:#}", error);
+        std::process::exit(1);
+    }
 }
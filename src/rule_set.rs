@@ -0,0 +1,127 @@
+//! Variants of the game mechanics the solver can follow.
+//!
+//! The default rules are the ones the solver has always used. The game does not stop a splice from giving
+//! a doubler a sibling, it only cannot mutate the doubler then, so the game rules differ from the default ones in that.
+//! Under both, a swap uses up a splice and only the shallowest special cells mutate, and the built-in levels are
+//! solved with their recorded optimal splice counts. The other variants can be picked one by one to test how they
+//! change solutions.
+
+use crate::cost_model::ActionCounts;
+use crate::level_solver::Action;
+use crate::strand::{CellKind, NodeId, Strand};
+
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct RuleSet
+{
+    pub doublerRule: DoublerRule,
+    pub swapRule: SwapRule,
+    pub mutationRule: MutationRule
+}
+
+impl RuleSet
+{
+    // Follows the game: a splice may give a doubler a sibling or move a doubler next to another cell, but the
+    // strand cannot mutate until the doubler is alone again. Swapping children uses up a splice like moving a cell
+    // does, and a mutation changes every special cell at the shallowest depth holding one, all at once.
+    pub(crate) const GAME: Self =
+        Self{doublerRule: DoublerRule::BlockMutation, swapRule: SwapRule::Splice, mutationRule: MutationRule::Shallowest};
+
+    // Checks only the children of the new parent, the caller makes sure the node does not move into its own subtree.
+    pub(crate) fn canJoin(&self, nodeId: NodeId, newParentId: NodeId, strand: &Strand) -> bool
+    {
+        match strand.childIds(newParentId) {
+            [] => true,
+            [childId] => match self.doublerRule {
+                DoublerRule::Alone => {
+                    strand.cellKind(*childId) != CellKind::Doubler && strand.cellKind(nodeId) != CellKind::Doubler
+                },
+                DoublerRule::BlockMutation => true
+            },
+            [_, _] => false,
+            _ => panic!("Cell can't have more than two children")
+        }
+    }
+
+    pub(crate) fn countActions(&self, action: &Action) -> ActionCounts
+    {
+        match (action, self.swapRule) {
+            (Action::SwapChildren{..}, SwapRule::Free) => ActionCounts::FREE_SWAP,
+            (Action::ChangeParent{..} | Action::SwapChildren{..}, _) => ActionCounts::SPLICE,
+            (Action::Mutate{..}, _) => ActionCounts::MUTATION
+        }
+    }
+
+    // Returns the cells a mutation changes in the order they change, or nothing when the strand cannot mutate.
+    pub(crate) fn findCellsToMutate(&self, strand: &Strand) -> Vec<NodeId>
+    {
+        let cellIds = match self.mutationRule {
+            MutationRule::Shallowest => strand.findShallowestSpecialCellIds(),
+            MutationRule::AllDeepestFirst => strand.findSpecialCellIdsDeepestFirst()
+        };
+        // A doubler puts its copy next to itself, so it has to be the only child of its parent.
+        let isBlocked = cellIds.iter().any(|cellId| strand.cellKind(*cellId) == CellKind::Doubler
+            && strand.parentId(*cellId).is_some_and(|parentId| strand.childCount(parentId) > 1));
+        match isBlocked {
+            true => vec![],
+            false => cellIds
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum DoublerRule
+{
+    // Splices can neither give a doubler a sibling nor move a doubler next to another cell.
+    #[default]
+    Alone,
+    // Splices can give a doubler a sibling, but the strand cannot mutate until the doubler is alone again.
+    BlockMutation
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum SwapRule
+{
+    #[default]
+    Splice,
+    Free
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum MutationRule
+{
+    #[default]
+    Shallowest,
+    AllDeepestFirst
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::level_maker::Level;
+    use crate::level_solver::{SearchEnd, solveLevelWithStatistics, SolverOptions};
+    use crate::strand_notation::parseStrand;
+
+    #[test]
+    fn onlyGameRulesLetADoublerHaveASibling()
+    {
+        // The target has a doubler with a sibling, which the default rules never make.
+        let makeLevel = || Level{
+            start: parseStrand("(((D))())").unwrap(),
+            target: parseStrand("(((D)()))").unwrap(),
+            maxSplices: 2,
+            optimalSplices: None};
+        let (result, _statistics) = solveLevelWithStatistics(makeLevel(), &SolverOptions::default());
+        assert_eq!(result.unwrap_err().searchEnd, SearchEnd::Exhausted);
+        let options = SolverOptions{rules: RuleSet::GAME, ..SolverOptions::default()};
+        let (result, _statistics) = solveLevelWithStatistics(makeLevel(), &options);
+        assert_eq!(result.unwrap().spliceCount, 1);
+
+        // Under both rules, the doubler cannot mutate while it has a sibling.
+        let strand = parseStrand("(((D)()))").unwrap();
+        assert!(RuleSet::default().findCellsToMutate(&strand).is_empty());
+        assert!(RuleSet::GAME.findCellsToMutate(&strand).is_empty());
+        assert_eq!(RuleSet::GAME.findCellsToMutate(&parseStrand("(((D))())").unwrap()), [2]);
+    }
+}
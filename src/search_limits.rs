@@ -0,0 +1,64 @@
+use crate::strand::MAX_NODE_COUNT;
+
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SearchLimits
+{
+    pub timeLimitOpt: Option<Duration>,
+    pub maxExpandedStatesOpt: Option<usize>,
+    // Strands which would need more nodes after a mutation are not mutated. It cannot exceed MAX_NODE_COUNT.
+    pub maxNodeCount: usize
+}
+
+impl Default for SearchLimits
+{
+    fn default() -> Self
+    {
+        Self{timeLimitOpt: None, maxExpandedStatesOpt: None, maxNodeCount: MAX_NODE_COUNT}
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum SearchLimit
+{
+    Time,
+    ExpandedStates,
+    NodeCount
+}
+
+impl Display for SearchLimit
+{
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            SearchLimit::Time => write!(formatter, "time limit"),
+            SearchLimit::ExpandedStates => write!(formatter, "limit of expanded states"),
+            SearchLimit::NodeCount => write!(formatter, "limit of nodes in a strand")
+        }
+    }
+}
+
+// Lets another thread stop a running search. Clones share the same state.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CancellationToken
+{
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancellationToken
+{
+    pub(crate) fn cancel(&self)
+    {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn isCancelled(&self) -> bool
+    {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
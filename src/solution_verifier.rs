@@ -0,0 +1,291 @@
+//! Replays a list of actions on the start strand of a level with the rules of the game.
+//!
+//! Actions can be read from text written the same way the solver prints them, one action per line,
+//! for example `Change parent of node 3 from 2 to 1 on the left`, `Swap children of parent node 0` or
+//! `Mutate nodes 1 and 4`. A moved node becomes the right child of its new parent unless `on the left` is given.
+//! Everything after a `#` or a `:` is ignored, so action lines printed by the solver can be used as they are,
+//! and blank lines are skipped.
+
+use crate::level_maker::{Level, SpliceCount};
+use crate::level_solver::{Action, findChildrenRecursively};
+use crate::rule_set::RuleSet;
+use crate::strand::{ChildSlot, MAX_NODE_COUNT, NodeId, Strand, SurfaceMatching};
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use to_trait::To;
+
+
+pub(crate) fn verifySolution(level: &Level, actions: &[Action], rules: &RuleSet, matching: SurfaceMatching)
+    -> Verification
+{
+    let mut verification = Verification{
+        strand: level.start.clone(), spliceCount: 0, mutationCount: 0, outcome: VerificationOutcome::TargetNotReached};
+    for (stepIndex, action) in actions.iter().enumerate() {
+        if let Err(violation) = verification.apply(action, level.maxSplices, rules) {
+            verification.outcome = VerificationOutcome::IllegalStep{stepNumber: stepIndex + 1, violation};
+            return verification;
+        }
+    }
+    if verification.strand.matchesSurface(&level.target, matching) {
+        verification.outcome = VerificationOutcome::TargetReached;
+    }
+    verification
+}
+
+#[derive(Debug)]
+pub(crate) struct Verification
+{
+    // The last strand reached by legal actions.
+    pub strand: Strand,
+    pub spliceCount: SpliceCount,
+    pub mutationCount: usize,
+    pub outcome: VerificationOutcome
+}
+
+impl Verification
+{
+    fn apply(&mut self, action: &Action, maxSplices: SpliceCount, rules: &RuleSet) -> Result<(), RuleViolation>
+    {
+        let usedSplices = rules.countActions(action).splices.try_to::<SpliceCount>().unwrap();
+        if usedSplices > 0 && self.spliceCount >= maxSplices {
+            return Err(RuleViolation::SpliceBudgetExceeded{maxSplices});
+        }
+        match action {
+            Action::ChangeParent{node, oldParent, newParent, slot} => {
+                checkParentChange(&self.strand, *node, *oldParent, *newParent, rules)?;
+                self.strand.changeParent(*node, *newParent, *slot);
+            },
+            Action::SwapChildren{parent} => {
+                checkNodeExists(&self.strand, *parent)?;
+                if self.strand.childCount(*parent) != 2 {
+                    return Err(RuleViolation::NotTwoChildren{parent: *parent});
+                }
+                self.strand.swapChildren(*parent);
+            },
+            Action::Mutate{nodes} => {
+                let cellIds = rules.findCellsToMutate(&self.strand);
+                if cellIds.is_empty() {
+                    return Err(RuleViolation::NothingToMutate);
+                }
+                if self.strand.nodeCountAfterMutation(&cellIds) > MAX_NODE_COUNT {
+                    return Err(RuleViolation::TooManyNodes);
+                }
+                let mut sortedNodes = nodes.clone();
+                sortedNodes.sort_unstable();
+                let mut sortedCellIds = cellIds.clone();
+                sortedCellIds.sort_unstable();
+                if sortedNodes != sortedCellIds {
+                    return Err(RuleViolation::WrongMutatedNodes{expected: cellIds});
+                }
+                self.strand.mutate(&cellIds);
+                self.mutationCount += 1;
+            }
+        }
+        self.spliceCount += usedSplices;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum VerificationOutcome
+{
+    TargetReached,
+    TargetNotReached,
+    IllegalStep{stepNumber: usize, violation: RuleViolation}
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum RuleViolation
+{
+    MissingNode{node: NodeId},
+    NotChildOf{node: NodeId, parent: NodeId},
+    SameParent{node: NodeId, parent: NodeId},
+    DescendantAsParent{node: NodeId, newParent: NodeId},
+    FullParent{newParent: NodeId},
+    DoublerSibling{node: NodeId, newParent: NodeId},
+    NotTwoChildren{parent: NodeId},
+    SpliceBudgetExceeded{maxSplices: SpliceCount},
+    NothingToMutate,
+    TooManyNodes,
+    WrongMutatedNodes{expected: Vec<NodeId>}
+}
+
+impl Display for RuleViolation
+{
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            RuleViolation::MissingNode{node} => write!(formatter, "node {} is not in the strand", node),
+            RuleViolation::NotChildOf{node, parent} => write!(formatter, "node {} is not a child of node {}", node, parent),
+            RuleViolation::SameParent{node, parent} => write!(formatter, "node {} is already a child of node {}", node, parent),
+            RuleViolation::DescendantAsParent{node, newParent} => {
+                write!(formatter, "node {} cannot become a child of node {} inside its own subtree", node, newParent)
+            },
+            RuleViolation::FullParent{newParent} => write!(formatter, "node {} already has two children", newParent),
+            RuleViolation::DoublerSibling{node, newParent} => {
+                write!(formatter, "node {} cannot join the child of node {}, a doubler cannot have a sibling", node, newParent)
+            },
+            RuleViolation::NotTwoChildren{parent} => write!(formatter, "node {} does not have two children to swap", parent),
+            RuleViolation::SpliceBudgetExceeded{maxSplices} => {
+                write!(formatter, "all {} splices of the level are used up", maxSplices)
+            },
+            RuleViolation::NothingToMutate => write!(formatter, "no special cell of the strand can mutate"),
+            RuleViolation::TooManyNodes => write!(formatter, "the mutation would grow the strand beyond {} nodes", MAX_NODE_COUNT),
+            RuleViolation::WrongMutatedNodes{expected} => {
+                let nodes = expected.iter().map(|node| node.to_string()).collect::<Vec<_>>().join(", ");
+                write!(formatter, "a mutation changes exactly the nodes {}", nodes)
+            }
+        }
+    }
+}
+
+// The same rules as the solver follows when looking for new parents.
+fn checkParentChange(strand: &Strand, node: NodeId, oldParent: NodeId, newParent: NodeId, rules: &RuleSet)
+    -> Result<(), RuleViolation>
+{
+    checkNodeExists(strand, node)?;
+    checkNodeExists(strand, newParent)?;
+    if strand.parentId(node) != Some(oldParent) {
+        return Err(RuleViolation::NotChildOf{node, parent: oldParent});
+    }
+    if newParent == oldParent {
+        return Err(RuleViolation::SameParent{node, parent: oldParent});
+    }
+    if newParent == node || findChildrenRecursively(node, strand).contains(&newParent) {
+        return Err(RuleViolation::DescendantAsParent{node, newParent});
+    }
+    match strand.childCount(newParent) {
+        2 => Err(RuleViolation::FullParent{newParent}),
+        _ if !rules.canJoin(node, newParent, strand) => Err(RuleViolation::DoublerSibling{node, newParent}),
+        _ => Ok(())
+    }
+}
+
+fn checkNodeExists(strand: &Strand, node: NodeId) -> Result<(), RuleViolation>
+{
+    match strand.collectNodeIds().contains(&node) {
+        true => Ok(()),
+        false => Err(RuleViolation::MissingNode{node})
+    }
+}
+
+pub(crate) fn loadActionFile(path: &Path) -> Result<Vec<Action>>
+{
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read action file {}", path.display()))?;
+    parseActions(&text).with_context(|| format!("Failed to parse action file {}", path.display()))
+}
+
+pub(crate) fn parseActions(text: &str) -> Result<Vec<Action>>
+{
+    let mut actions = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let content = line.split(['#', ':']).next().unwrap().trim();
+        if content.is_empty() {
+            continue;
+        }
+        actions.push(parseAction(content).with_context(|| format!("Line {}", index + 1))?);
+    }
+    Ok(actions)
+}
+
+fn parseAction(text: &str) -> Result<Action>
+{
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    match words[..] {
+        ["Change", "parent", "of", "node", node, "from", oldParent, "to", newParent, ref slot @ ..] => {
+            let slot = match slot {
+                [] | ["on", "the", "right"] => ChildSlot::Right,
+                ["on", "the", "left"] => ChildSlot::Left,
+                _ => bail!("Expected the new parent to be followed by on the left or on the right: {}", text)
+            };
+            Ok(Action::ChangeParent{
+                node: parseNodeId(node)?, oldParent: parseNodeId(oldParent)?, newParent: parseNodeId(newParent)?, slot})
+        },
+        ["Swap", "children", "of", "parent", "node", parent] => Ok(Action::SwapChildren{parent: parseNodeId(parent)?}),
+        ["Mutate", "node", node] => Ok(Action::Mutate{nodes: vec![parseNodeId(node)?]}),
+        ["Mutate", "nodes", ref nodes @ ..] => {
+            let nodes = nodes.iter()
+                .map(|word| word.trim_end_matches(','))
+                .filter(|word| *word != "and")
+                .map(parseNodeId)
+                .collect::<Result<Vec<_>>>()?;
+            if nodes.len() < 2 {
+                bail!("Expected at least 2 nodes to mutate: {}", text);
+            }
+            Ok(Action::Mutate{nodes})
+        },
+        _ => bail!("Unknown action: {}", text)
+    }
+}
+
+fn parseNodeId(text: &str) -> Result<NodeId>
+{
+    text.parse().map_err(|_| anyhow!("Invalid node id: {}", text))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::strand_notation::{formatStrand, parseStrand};
+
+    #[test]
+    fn actionsArePrintedAndParsedAlike()
+    {
+        let text = "Change parent of node 3 from 2 to 1 on the left: ((()))\n\n# Comment\n\
+                    Swap children of parent node 0\nChange parent of node 4 from 0 to 2\n\
+                    Mutate node 5\nMutate nodes 1, 2 and 6";
+        let actions = parseActions(text).unwrap();
+        assert_eq!(actions, [
+            Action::ChangeParent{node: 3, oldParent: 2, newParent: 1, slot: ChildSlot::Left},
+            Action::SwapChildren{parent: 0},
+            Action::ChangeParent{node: 4, oldParent: 0, newParent: 2, slot: ChildSlot::Right},
+            Action::Mutate{nodes: vec![5]},
+            Action::Mutate{nodes: vec![1, 2, 6]}]);
+        let printedText = actions.iter().map(|action| action.to_string()).collect::<Vec<_>>().join("\n");
+        assert_eq!(parseActions(&printedText).unwrap(), actions);
+
+        let error = parseActions("Swap children of parent node 0\nSwap children of node 1").unwrap_err();
+        assert_eq!(format!("{:#}", error), "Line 2: Unknown action: Swap children of node 1");
+    }
+
+    #[test]
+    fn illegalActionsAreReportedWithTheirStep()
+    {
+        let rules = RuleSet::default();
+        assert_eq!(verifyActions("Change parent of node 2 from 0 to 3", &rules),
+                   Err((1, RuleViolation::NotChildOf{node: 2, parent: 0})));
+        assert_eq!(verifyActions("Change parent of node 3 from 0 to 1", &rules),
+                   Err((1, RuleViolation::DoublerSibling{node: 3, newParent: 1})));
+        assert_eq!(verifyActions("Change parent of node 3 from 0 to 1", &RuleSet::GAME),
+                   Ok(("(((D)()))".into(), false)));
+        assert_eq!(verifyActions("Swap children of parent node 0\nSwap children of parent node 0", &rules),
+                   Err((2, RuleViolation::SpliceBudgetExceeded{maxSplices: 1})));
+    }
+
+    #[test]
+    fn targetIsReachedOnlyByTheRightFinalStrand()
+    {
+        let rules = RuleSet::default();
+        assert_eq!(verifyActions("", &rules), Ok(("(((D))())".into(), false)));
+        assert_eq!(verifyActions("Swap children of parent node 0", &rules), Ok(("(()((D)))".into(), true)));
+    }
+
+    // Returns the final strand and whether it is the target, or the number of the illegal step and its violation.
+    fn verifyActions(text: &str, rules: &RuleSet) -> Result<(String, bool), (usize, RuleViolation)>
+    {
+        let level = Level{
+            start: parseStrand("(((D))())").unwrap(),
+            target: parseStrand("(()((D)))").unwrap(),
+            maxSplices: 1,
+            optimalSplices: None};
+        let verification = verifySolution(&level, &parseActions(text).unwrap(), rules, SurfaceMatching::Ordered);
+        match verification.outcome {
+            VerificationOutcome::TargetReached => Ok((formatStrand(&verification.strand), true)),
+            VerificationOutcome::TargetNotReached => Ok((formatStrand(&verification.strand), false)),
+            VerificationOutcome::IllegalStep{stepNumber, violation} => Err((stepNumber, violation))
+        }
+    }
+}
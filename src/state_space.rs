@@ -0,0 +1,259 @@
+//! Enumerates every strand reachable from the start of a level within its splice allowance.
+//!
+//! States are strands told apart by their canonical shape, each reached with the fewest splices possible.
+//! A state is a dead end when the target cannot be reached from it with the splices left, which is only known when
+//! the exploration was not cut short by a search limit or cancelled. The node count limit does not stop
+//! the exploration, but it leaves out mutations, so it keeps dead ends unmarked as well.
+//! The graph can be exported as DOT or as a list with one line per state and per edge:
+//! `state <index> <splices> <goal|dead-end|open> <strand>` and `edge <from> <to> <splices> <action>`,
+//! where an edge uses the given number of splices and its action refers to node ids of the strand it starts from.
+
+use crate::level_maker::{Level, SpliceCount};
+use crate::level_solver::{Action, isNodeCountExceededByMutation, makeSuccessors, SearchEnd, SolutionStep, SolverOptions};
+use crate::search_limits::SearchLimit;
+use crate::strand_notation::formatStrand;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::time::Instant;
+
+
+pub(crate) fn exploreStateSpace(level: &Level, options: &SolverOptions) -> StateSpace
+{
+    let startTime = Instant::now();
+    let startStep = SolutionStep::new(level.start.clone(), None, 0);
+    let mut stateSpace = StateSpace{states: vec![], edges: vec![], endOpt: None};
+    let mut stateIndices = HashMap::from([(startStep.shape.clone(), 0)]);
+    stateSpace.states.push(ExploredState::new(startStep, level, options));
+    // Splices are the only actions which cost anything here, so a deque keeps the states ordered by their splices.
+    let mut queue = VecDeque::from([0]);
+    let mut expandedStates = HashSet::new();
+    while let Some(stateIndex) = queue.pop_front() {
+        if expandedStates.contains(&stateIndex) {
+            continue;
+        }
+        if let Some(end) = findInterruption(options, startTime, expandedStates.len()) {
+            stateSpace.endOpt = Some(end);
+            break;
+        }
+        expandedStates.insert(stateIndex);
+
+        let step = stateSpace.states[stateIndex].step.clone();
+        let cellIds = options.rules.findCellsToMutate(&step.strand);
+        if isNodeCountExceededByMutation(&step.strand, &cellIds, options.limits.maxNodeCount) {
+            stateSpace.endOpt.get_or_insert(SearchEnd::LimitReached(SearchLimit::NodeCount));
+        }
+        let mut successorIndices = HashSet::new();
+        for (successor, _cost) in makeSuccessors(&step, level.maxSplices, options) {
+            let usedSplices = successor.spliceCount - step.spliceCount;
+            let successorIndex = match stateIndices.get(&successor.shape) {
+                Some(successorIndex) => {
+                    let successorState = &mut stateSpace.states[*successorIndex];
+                    if successor.spliceCount < successorState.step.spliceCount {
+                        successorState.step = successor.clone();
+                        pushState(&mut queue, *successorIndex, usedSplices);
+                    }
+                    *successorIndex
+                },
+                None => {
+                    let successorIndex = stateSpace.states.len();
+                    stateIndices.insert(successor.shape.clone(), successorIndex);
+                    stateSpace.states.push(ExploredState::new(successor.clone(), level, options));
+                    pushState(&mut queue, successorIndex, usedSplices);
+                    successorIndex
+                }
+            };
+            // Different actions leading to the same shape make a single edge, and actions keeping the shape make none.
+            if successorIndex != stateIndex && successorIndices.insert(successorIndex) {
+                stateSpace.edges.push(StateEdge{
+                    fromIndex: stateIndex, toIndex: successorIndex, usedSplices, action: successor.lastAction.unwrap()});
+            }
+        }
+    }
+    if stateSpace.endOpt.is_none() {
+        stateSpace.markDeadEnds(level.maxSplices);
+    }
+    stateSpace
+}
+
+fn pushState(queue: &mut VecDeque<usize>, stateIndex: usize, usedSplices: SpliceCount)
+{
+    match usedSplices {
+        0 => queue.push_front(stateIndex),
+        _ => queue.push_back(stateIndex)
+    }
+}
+
+// Follows the solver, which expands at most the given number of states.
+fn findInterruption(options: &SolverOptions, startTime: Instant, expandedStateCount: usize) -> Option<SearchEnd>
+{
+    let limits = &options.limits;
+    if options.cancellationToken.isCancelled() {
+        Some(SearchEnd::Cancelled)
+    } else if limits.timeLimitOpt.is_some_and(|timeLimit| startTime.elapsed() > timeLimit) {
+        Some(SearchEnd::LimitReached(SearchLimit::Time))
+    } else if limits.maxExpandedStatesOpt.is_some_and(|maxStates| expandedStateCount >= maxStates) {
+        Some(SearchEnd::LimitReached(SearchLimit::ExpandedStates))
+    } else {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct StateSpace
+{
+    pub states: Vec<ExploredState>,
+    pub edges: Vec<StateEdge>,
+    // Why the exploration was cut short or some mutations were left out, the state space is complete without it.
+    pub endOpt: Option<SearchEnd>
+}
+
+impl StateSpace
+{
+    pub(crate) fn countStates(&self, kind: StateKind) -> usize
+    {
+        self.states.iter().filter(|state| state.kind == kind).count()
+    }
+
+    pub(crate) fn formatList(&self) -> String
+    {
+        let mut output = String::new();
+        for (index, state) in self.states.iter().enumerate() {
+            output.push_str(&format!(
+                "state {} {} {} {}\n", index, state.step.spliceCount, state.kind, formatStrand(&state.step.strand)));
+        }
+        for edge in &self.edges {
+            output.push_str(&format!("edge {} {} {} {}\n", edge.fromIndex, edge.toIndex, edge.usedSplices, edge.action));
+        }
+        output
+    }
+
+    // The fewest splices to a goal are found backward from the goal states.
+    fn markDeadEnds(&mut self, maxSplices: SpliceCount)
+    {
+        let mut incomingEdges = vec![vec![]; self.states.len()];
+        for edge in &self.edges {
+            incomingEdges[edge.toIndex].push(edge);
+        }
+        let mut splicesToGoal = vec![None; self.states.len()];
+        let mut queue = VecDeque::new();
+        for (index, state) in self.states.iter().enumerate() {
+            if state.kind == StateKind::Goal {
+                splicesToGoal[index] = Some(0);
+                queue.push_back(index);
+            }
+        }
+        while let Some(index) = queue.pop_front() {
+            let splices = splicesToGoal[index].unwrap();
+            for edge in &incomingEdges[index] {
+                let newSplices = splices + edge.usedSplices;
+                if splicesToGoal[edge.fromIndex].is_none_or(|oldSplices| newSplices < oldSplices) {
+                    splicesToGoal[edge.fromIndex] = Some(newSplices);
+                    pushState(&mut queue, edge.fromIndex, edge.usedSplices);
+                }
+            }
+        }
+        for (state, splicesToGoal) in self.states.iter_mut().zip(splicesToGoal) {
+            if splicesToGoal.is_none_or(|splices| state.step.spliceCount + splices > maxSplices) {
+                state.kind = StateKind::DeadEnd;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ExploredState
+{
+    // The step with the fewest splices which reaches the state.
+    pub step: SolutionStep,
+    pub kind: StateKind
+}
+
+impl ExploredState
+{
+    fn new(step: SolutionStep, level: &Level, options: &SolverOptions) -> Self
+    {
+        let kind = match step.strand.matchesSurface(&level.target, options.matching) {
+            true => StateKind::Goal,
+            false => StateKind::Open
+        };
+        Self{step, kind}
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum StateKind
+{
+    Goal,
+    DeadEnd,
+    Open
+}
+
+impl Display for StateKind
+{
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            StateKind::Goal => write!(formatter, "goal"),
+            StateKind::DeadEnd => write!(formatter, "dead-end"),
+            StateKind::Open => write!(formatter, "open")
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct StateEdge
+{
+    pub fromIndex: usize,
+    pub toIndex: usize,
+    pub usedSplices: SpliceCount,
+    pub action: Action
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::search_limits::SearchLimits;
+    use crate::strand_notation::parseStrand;
+
+    #[test]
+    fn completeExplorationMarksDeadEndsAndHasNoSelfLoops()
+    {
+        // Swapping the two leaves of node 1 keeps the shape of the start.
+        let stateSpace = exploreStateSpace(&makeLevel("((()())())", "(((()))())", 1), &SolverOptions::default());
+        assert_eq!(stateSpace.endOpt, None);
+        assert!(stateSpace.countStates(StateKind::Goal) > 0);
+        assert!(stateSpace.countStates(StateKind::DeadEnd) > 0);
+        assert!(stateSpace.edges.iter().all(|edge| edge.fromIndex != edge.toIndex));
+    }
+
+    #[test]
+    fn truncatedExplorationLeavesDeadEndsUnmarked()
+    {
+        let limits = SearchLimits{maxExpandedStatesOpt: Some(1), ..SearchLimits::default()};
+        let options = SolverOptions{limits, ..SolverOptions::default()};
+        let stateSpace = exploreStateSpace(&makeLevel("((())())", "(((())))", 1), &options);
+        assert_eq!(stateSpace.endOpt, Some(SearchEnd::LimitReached(SearchLimit::ExpandedStates)));
+        assert!(stateSpace.edges.iter().all(|edge| edge.fromIndex == 0));
+        assert_eq!(stateSpace.countStates(StateKind::DeadEnd), 0);
+
+        let options = SolverOptions::default();
+        options.cancellationToken.cancel();
+        let stateSpace = exploreStateSpace(&makeLevel("((())())", "(((())))", 1), &options);
+        assert_eq!(stateSpace.endOpt, Some(SearchEnd::Cancelled));
+        assert!(stateSpace.edges.is_empty());
+
+        // The extender cannot mutate within the node count limit, so the exploration misses the strands it leads to.
+        let limits = SearchLimits{maxNodeCount: 2, ..SearchLimits::default()};
+        let options = SolverOptions{limits, ..SolverOptions::default()};
+        let stateSpace = exploreStateSpace(&makeLevel("((E))", "((()))", 1), &options);
+        assert_eq!(stateSpace.endOpt, Some(SearchEnd::LimitReached(SearchLimit::NodeCount)));
+        assert_eq!(stateSpace.countStates(StateKind::DeadEnd), 0);
+    }
+
+    fn makeLevel(start: &str, target: &str, maxSplices: SpliceCount) -> Level
+    {
+        Level{start: parseStrand(start).unwrap(), target: parseStrand(target).unwrap(), maxSplices, optimalSplices: None}
+    }
+}
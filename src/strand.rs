@@ -1,14 +1,20 @@
+use anyhow::{bail, Result};
 use arrayvec::ArrayVec;
 use fixedbitset::FixedBitSet;
 use itertools::Itertools as _;
 use petgraph::visit::{Dfs, GraphBase, IntoNeighbors, Visitable};
+use std::cmp::Reverse;
 use std::collections::BTreeMap;
 use to_trait::To;
 
 pub(crate) type NodeId = u8;
+pub(crate) const MAX_NODE_COUNT: usize = NodeId::MAX as usize + 1;
+pub(crate) const MAX_CHILD_COUNT: usize = 2;
 pub(crate) type Edge = (NodeId, NodeId);
 type Depth = usize;
 
+const CHILD_COUNT_CODES: u8 = 3;
+
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub(crate) struct Strand
@@ -18,7 +24,13 @@ pub(crate) struct Strand
 
 impl Strand
 {
-    pub(crate) fn new(nodeCount: usize, edges: &[Edge], mutables: &[(NodeId,CellKind)]) -> Self
+    pub(crate) fn tryNew(nodeCount: usize, edges: &[Edge], mutables: &[(NodeId,CellKind)]) -> Result<Self>
+    {
+        validateDefinition(nodeCount, edges, mutables)?;
+        Ok(Self::new(nodeCount, edges, mutables))
+    }
+
+    fn new(nodeCount: usize, edges: &[Edge], mutables: &[(NodeId,CellKind)]) -> Self
     {
         let mut newSelf = Self{nodes: vec![Some(Node::default()); nodeCount]};
         for edge in edges {
@@ -67,11 +79,14 @@ impl Strand
         self.collectEdgesFrom(Self::root())
     }
 
-    pub(crate) fn changeParent(&mut self, childId: NodeId, newParentId: NodeId)
+    pub(crate) fn changeParent(&mut self, childId: NodeId, newParentId: NodeId, slot: ChildSlot)
     {
         debug_assert_ne!(self.parentId(childId), Some(newParentId));
         self.disconnectParentFromChild(childId);
         self.connectParentToChild(newParentId, childId);
+        if slot == ChildSlot::Left {
+            self.nodeAtMut(newParentId).childrenIds.rotate_right(1);
+        }
     }
 
     pub(crate) fn swapChildren(&mut self, nodeId: NodeId)
@@ -80,13 +95,9 @@ impl Strand
         self.nodeAtMut(nodeId).childrenIds.swap(0, 1);
     }
 
-    pub(crate) fn mutate(&mut self) -> Vec<NodeId>
+    pub(crate) fn mutate(&mut self, cellIds: &[NodeId])
     {
-        let mutableCellsIds = self.findMutableSpecialCellsIds();
-        if mutableCellsIds.is_empty() {
-            return vec![];
-        }
-        for cellId in &mutableCellsIds {
+        for cellId in cellIds {
             match self.cellKind(*cellId) {
                 CellKind::Doubler  => self.mutateDoubler(*cellId),
                 CellKind::Extender => self.mutateExtender(*cellId),
@@ -94,7 +105,63 @@ impl Strand
                 CellKind::Normal   => panic!("Cannot mutate a normal cell with id: {}", cellId)
             }
         }
-        mutableCellsIds
+    }
+
+    // Returns how many node ids the strand would use after mutating the given cells in order. Ids of erased cells
+    // are not reused, so they are counted as well, and so are nodes copied by a doubler after an eraser inside it.
+    pub(crate) fn nodeCountAfterMutation(&self, cellIds: &[NodeId]) -> usize
+    {
+        let mut addedNodeCounts = Vec::with_capacity(cellIds.len());
+        for cellId in cellIds {
+            let addedNodeCount = match self.cellKind(*cellId) {
+                CellKind::Doubler  => {
+                    let subtreeIds = self.collectNodeIdsFrom(*cellId);
+                    let addedInsideCount = cellIds.iter().zip(&addedNodeCounts)
+                        .filter(|(mutatedCellId, _)| subtreeIds.contains(mutatedCellId))
+                        .map(|(_, count)| count)
+                        .sum::<usize>();
+                    subtreeIds.len() + addedInsideCount
+                },
+                CellKind::Extender => 1,
+                CellKind::Eraser | CellKind::Normal => 0
+            };
+            addedNodeCounts.push(addedNodeCount);
+        }
+        self.nodeCount() + addedNodeCounts.iter().sum::<usize>()
+    }
+
+    // Encodes the surface of the strand, meaning its shape including the order of children and the kinds of cells,
+    // but not the ids of nodes. Two strands are equal on the surface exactly when their canonical shapes are equal.
+    pub(crate) fn canonicalShape(&self) -> CanonicalShape
+    {
+        let mut shape = Vec::with_capacity(self.nodeCount());
+        let mut dfs = Dfs::new(self, Self::root());
+        while let Some(nodeId) = dfs.next(self) {
+            shape.push(self.cellCode(nodeId));
+        }
+        CanonicalShape(shape)
+    }
+
+    // Like the canonical shape, but the same for strands which differ only in the order of children.
+    pub(crate) fn unorderedCanonicalShape(&self) -> CanonicalShape
+    {
+        CanonicalShape(self.encodeUnordered(Self::root()))
+    }
+
+    pub(crate) fn surfaceShape(&self, matching: SurfaceMatching) -> CanonicalShape
+    {
+        match matching {
+            SurfaceMatching::Ordered => self.canonicalShape(),
+            SurfaceMatching::Unordered => self.unorderedCanonicalShape()
+        }
+    }
+
+    pub(crate) fn matchesSurface(&self, other: &Self, matching: SurfaceMatching) -> bool
+    {
+        match matching {
+            SurfaceMatching::Ordered => self.isEqualOnSurface(other),
+            SurfaceMatching::Unordered => self.unorderedCanonicalShape() == other.unorderedCanonicalShape()
+        }
     }
 
     pub fn isEqualOnSurface(&self, other: &Self) -> bool
@@ -116,8 +183,29 @@ impl Strand
         otherDfs.next(other).is_none()
     }
 
+    // Counts the cells of both strands which have no counterpart of the same kind in the same place of the other
+    // strand, so it is 0 exactly when the strands are equal on the surface.
+    pub(crate) fn surfaceDistance(&self, other: &Self) -> usize
+    {
+        let matchingCellCount = countMatchingCells(self, Self::root(), other, Self::root());
+        self.collectNodeIds().len() + other.collectNodeIds().len() - 2 * matchingCellCount
+    }
+
     // private
 
+    fn cellCode(&self, nodeId: NodeId) -> u8
+    {
+        self.cellKind(nodeId).code() * CHILD_COUNT_CODES + self.childCount(nodeId).try_to::<u8>().unwrap()
+    }
+
+    // Every subtree is encoded in preorder, which needs no separators as each code tells how many children follow.
+    // Sorting the encodings of children makes the order of children irrelevant.
+    fn encodeUnordered(&self, nodeId: NodeId) -> Vec<u8>
+    {
+        let childEncodings = self.childIds(nodeId).iter().map(|childId| self.encodeUnordered(*childId)).sorted();
+        std::iter::once(self.cellCode(nodeId)).chain(childEncodings.flatten()).collect()
+    }
+
     fn nodeAt(&self, nodeId: NodeId) -> &Node
     {
         self.nodes[nodeId.to::<usize>()].as_ref().unwrap()
@@ -177,7 +265,7 @@ impl Strand
     }
 
     #[allow(clippy::comparison_chain)]
-    fn findMutableSpecialCellsIds(&self) -> Vec<NodeId>
+    pub(crate) fn findShallowestSpecialCellIds(&self) -> Vec<NodeId>
     {
         let mut shallowestDepth = usize::MAX;
         let mut output = vec![];
@@ -197,6 +285,20 @@ impl Strand
         output
     }
 
+    pub(crate) fn findSpecialCellIdsDeepestFirst(&self) -> Vec<NodeId>
+    {
+        self.collectNodeIds().into_iter()
+            .filter(|cellId| self.cellKind(*cellId) != CellKind::Normal)
+            .sorted_by_key(|cellId| Reverse(self.calculateDepth(*cellId)))
+            .collect()
+    }
+
+    // The depth of the deepest cell.
+    pub(crate) fn calculateHeight(&self) -> Depth
+    {
+        self.collectNodeIds().into_iter().map(|nodeId| self.calculateDepth(nodeId)).max().unwrap()
+    }
+
     fn calculateDepth(&self, nodeId: NodeId) -> Depth
     {
         let mut depth = 0;
@@ -250,12 +352,12 @@ impl Strand
         match childIds[..] {
             [] => self.connectParentToChild(extenderNodeId, newNodeId),
             [childId] => {
-                self.changeParent(childId, newNodeId);
+                self.changeParent(childId, newNodeId, ChildSlot::Right);
                 self.connectParentToChild(extenderNodeId, newNodeId);
             },
             [childId1, childId2] => {
-                self.changeParent(childId1, newNodeId);
-                self.changeParent(childId2, newNodeId);
+                self.changeParent(childId1, newNodeId, ChildSlot::Right);
+                self.changeParent(childId2, newNodeId, ChildSlot::Right);
                 self.connectParentToChild(extenderNodeId, newNodeId);
             },
             _ => panic!("Cell cannot have more than 2 children")
@@ -272,6 +374,91 @@ impl Strand
     }
 }
 
+fn validateDefinition(nodeCount: usize, edges: &[Edge], mutables: &[(NodeId,CellKind)]) -> Result<()>
+{
+    if nodeCount == 0 {
+        bail!("Strand must have at least 1 node.");
+    }
+    if nodeCount > MAX_NODE_COUNT {
+        bail!("Strand cannot have more than {} nodes, got {}.", MAX_NODE_COUNT, nodeCount);
+    }
+
+    let mut parentIds = vec![None; nodeCount];
+    let mut childIds = vec![vec![]; nodeCount];
+    for &(parentId, childId) in edges {
+        for nodeId in [parentId, childId] {
+            if nodeId.to::<usize>() >= nodeCount {
+                bail!("Edge {}-{} refers to node {}, but node ids must be lower than {}.",
+                      parentId, childId, nodeId, nodeCount);
+            }
+        }
+        if parentId == childId {
+            bail!("Edge {}-{} connects node {} to itself.", parentId, childId, parentId);
+        }
+        if childId == Strand::root() {
+            bail!("Edge {}-{} gives a parent to the root node {}.", parentId, childId, childId);
+        }
+        if let Some(oldParentId) = parentIds[childId.to::<usize>()] {
+            if oldParentId == parentId {
+                bail!("Edge {}-{} is defined more than once.", parentId, childId);
+            }
+            bail!("Node {} has more than one parent: {} and {}.", childId, oldParentId, parentId);
+        }
+        parentIds[childId.to::<usize>()] = Some(parentId);
+        childIds[parentId.to::<usize>()].push(childId);
+    }
+
+    for (nodeId, children) in childIds.iter().enumerate() {
+        if children.len() > MAX_CHILD_COUNT {
+            bail!("Node {} has more than {} children: {}.", nodeId, MAX_CHILD_COUNT, children.iter().join(", "));
+        }
+    }
+
+    for nodeId in 0..nodeCount {
+        let mut visitedIds = vec![nodeId];
+        let mut currentId = nodeId;
+        while let Some(parentId) = parentIds[currentId] {
+            currentId = parentId.to::<usize>();
+            if visitedIds.contains(&currentId) {
+                bail!("Nodes {} form a cycle.", visitedIds.iter().sorted().join(", "));
+            }
+            visitedIds.push(currentId);
+        }
+        if currentId != Strand::root().to::<usize>() {
+            bail!("Node {} is not reachable from the root node {}.", nodeId, Strand::root());
+        }
+    }
+
+    let mut mutableIds = vec![];
+    for &(nodeId, cellKind) in mutables {
+        if nodeId.to::<usize>() >= nodeCount {
+            bail!("Special cell {} is out of range, node ids must be lower than {}.", nodeId, nodeCount);
+        }
+        if mutableIds.contains(&nodeId) {
+            bail!("Node {} has its cell kind defined more than once.", nodeId);
+        }
+        mutableIds.push(nodeId);
+        if cellKind == CellKind::Normal {
+            continue;
+        }
+        if nodeId == Strand::root() {
+            bail!("Root node {} cannot be a special cell.", nodeId);
+        }
+    }
+    Ok(())
+}
+
+fn countMatchingCells(leftStrand: &Strand, leftNodeId: NodeId, rightStrand: &Strand, rightNodeId: NodeId) -> usize
+{
+    if !isCellKindTheSame(leftStrand, leftNodeId, rightStrand, rightNodeId) {
+        return 0;
+    }
+    let childrenMatchingCellCount = leftStrand.childIds(leftNodeId).iter().zip(rightStrand.childIds(rightNodeId))
+        .map(|(leftChildId, rightChildId)| countMatchingCells(leftStrand, *leftChildId, rightStrand, *rightChildId))
+        .sum::<usize>();
+    1 + childrenMatchingCellCount
+}
+
 fn isChildrenCountTheSame(leftStrand: &Strand, leftNodeId: NodeId, rightStrand: &Strand, rightNodeId: NodeId) -> bool
 {
     leftStrand.childCount(leftNodeId) == rightStrand.childCount(rightNodeId)
@@ -344,12 +531,37 @@ impl Iterator for ChildNodesIterator
     }
 }
 
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub(crate) struct CanonicalShape(Vec<u8>);
+
+// Whether the order of children matters when a strand is compared with the target.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum SurfaceMatching
+{
+    #[default]
+    Ordered,
+    Unordered
+}
+
+impl CanonicalShape
+{
+    pub(crate) fn cellCount(&self) -> usize
+    {
+        self.0.len()
+    }
+
+    pub(crate) fn hasSpecialCells(&self) -> bool
+    {
+        self.0.iter().any(|code| *code >= CHILD_COUNT_CODES)
+    }
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
 struct Node
 {
     cellKind: CellKind,
     parentIdOpt: Option<NodeId>,
-    childrenIds: ArrayVec<NodeId, 2>
+    childrenIds: ArrayVec<NodeId, MAX_CHILD_COUNT>
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
@@ -361,6 +573,27 @@ pub(crate) enum CellKind
     Eraser
 }
 
+impl CellKind
+{
+    pub(crate) fn code(self) -> u8
+    {
+        match self {
+            CellKind::Normal   => 0,
+            CellKind::Doubler  => 1,
+            CellKind::Extender => 2,
+            CellKind::Eraser   => 3
+        }
+    }
+}
+
+// Where a moved node goes among the children of its new parent. The only child of a parent is in the right slot.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub(crate) enum ChildSlot
+{
+    Left,
+    Right
+}
+
 impl Default for CellKind
 {
     fn default() -> Self
@@ -368,3 +601,65 @@ impl Default for CellKind
         CellKind::Normal
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn validDefinitionsAreAccepted()
+    {
+        assert!(Strand::tryNew(1, &[], &[]).is_ok());
+        assert!(Strand::tryNew(3, &[(0, 1), (1, 2)], &[(1, CellKind::Doubler), (2, CellKind::Normal)]).is_ok());
+        // A doubler with a sibling cannot mutate, which the rule set may allow.
+        assert!(Strand::tryNew(3, &[(0, 1), (0, 2)], &[(1, CellKind::Doubler)]).is_ok());
+        assert!(Strand::tryNew(MAX_NODE_COUNT, &chainEdges(MAX_NODE_COUNT), &[]).is_ok());
+    }
+
+    #[test]
+    fn invalidNodeCountsAreRejected()
+    {
+        assert_eq!(validationError(0, &[], &[]), "Strand must have at least 1 node.");
+        assert_eq!(validationError(MAX_NODE_COUNT + 1, &chainEdges(MAX_NODE_COUNT), &[]),
+                   format!("Strand cannot have more than {} nodes, got {}.", MAX_NODE_COUNT, MAX_NODE_COUNT + 1));
+    }
+
+    #[test]
+    fn invalidEdgesAreRejected()
+    {
+        assert_eq!(validationError(2, &[(0, 2)], &[]), "Edge 0-2 refers to node 2, but node ids must be lower than 2.");
+        assert_eq!(validationError(2, &[(1, 1)], &[]), "Edge 1-1 connects node 1 to itself.");
+        assert_eq!(validationError(2, &[(1, 0)], &[]), "Edge 1-0 gives a parent to the root node 0.");
+        assert_eq!(validationError(2, &[(0, 1), (0, 1)], &[]), "Edge 0-1 is defined more than once.");
+        assert_eq!(validationError(3, &[(0, 1), (0, 2), (1, 2)], &[]), "Node 2 has more than one parent: 0 and 1.");
+        assert_eq!(validationError(4, &[(0, 1), (0, 2), (0, 3)], &[]), "Node 0 has more than 2 children: 1, 2, 3.");
+    }
+
+    #[test]
+    fn disconnectedNodesAreRejected()
+    {
+        assert_eq!(validationError(3, &[(1, 2), (2, 1)], &[]), "Nodes 1, 2 form a cycle.");
+        assert_eq!(validationError(3, &[(0, 1)], &[]), "Node 2 is not reachable from the root node 0.");
+    }
+
+    #[test]
+    fn invalidSpecialCellsAreRejected()
+    {
+        assert_eq!(validationError(2, &[(0, 1)], &[(2, CellKind::Eraser)]),
+                   "Special cell 2 is out of range, node ids must be lower than 2.");
+        assert_eq!(validationError(2, &[(0, 1)], &[(1, CellKind::Eraser), (1, CellKind::Extender)]),
+                   "Node 1 has its cell kind defined more than once.");
+        assert_eq!(validationError(2, &[(0, 1)], &[(0, CellKind::Doubler)]), "Root node 0 cannot be a special cell.");
+    }
+
+    fn validationError(nodeCount: usize, edges: &[Edge], mutables: &[(NodeId,CellKind)]) -> String
+    {
+        Strand::tryNew(nodeCount, edges, mutables).unwrap_err().to_string()
+    }
+
+    fn chainEdges(nodeCount: usize) -> Vec<Edge>
+    {
+        (1..nodeCount).map(|childId| ((childId - 1).try_to().unwrap(), childId.try_to().unwrap())).collect()
+    }
+}
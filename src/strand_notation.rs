@@ -0,0 +1,228 @@
+//! Nested bracket notation for strands.
+//!
+//! Every cell is written as a pair of brackets enclosing its children from left to right, for example
+//! `((D)(X(E)))` is a root with a doubler on the left and an eraser with an extender child on the right.
+//! A cell kind marker may follow the opening bracket: `D` for a doubler, `E` for an extender and `X`
+//! for an eraser, lowercase markers are accepted too. Whitespace is ignored.
+
+use crate::level_maker::StrandInfo;
+use crate::strand::{CellKind, MAX_CHILD_COUNT, MAX_NODE_COUNT, NodeId, Strand};
+
+use anyhow::{anyhow, bail, Result};
+use to_trait::To;
+
+
+pub(crate) fn parseStrand(text: &str) -> Result<Strand>
+{
+    let info = parseStrandNotation(text)?;
+    Strand::tryNew(info.nodeCount, &info.edges, &info.mutables)
+}
+
+pub(crate) fn parseStrandNotation(text: &str) -> Result<StrandInfo>
+{
+    let mut parser = Parser{
+        tokenizer: BracketTokenizer::new(text, "strand"),
+        info: StrandInfo{nodeCount: 0, edges: vec![], mutables: vec![]}};
+    parser.parseNode(None)?;
+    parser.tokenizer.expectEnd()?;
+    Ok(parser.info)
+}
+
+pub(crate) fn formatStrand(strand: &Strand) -> String
+{
+    let mut output = String::new();
+    formatNode(strand, Strand::root(), &mut output);
+    output
+}
+
+fn formatNode(strand: &Strand, nodeId: NodeId, output: &mut String)
+{
+    output.push('(');
+    match strand.cellKind(nodeId) {
+        CellKind::Normal => (),
+        CellKind::Doubler => output.push('D'),
+        CellKind::Extender => output.push('E'),
+        CellKind::Eraser => output.push('X')
+    }
+    for childId in strand.childIds(nodeId) {
+        formatNode(strand, *childId, output);
+    }
+    output.push(')');
+}
+
+struct Parser
+{
+    tokenizer: BracketTokenizer,
+    info: StrandInfo
+}
+
+impl Parser
+{
+    fn parseNode(&mut self, parentIdOpt: Option<NodeId>) -> Result<()>
+    {
+        self.tokenizer.expect('(')?;
+        let nodeId = self.info.nodeCount.try_to::<NodeId>()
+            .map_err(|_| anyhow!("Strand has more than {} cells.", MAX_NODE_COUNT))?;
+        self.info.nodeCount += 1;
+        if let Some(parentId) = parentIdOpt {
+            self.info.edges.push((parentId, nodeId));
+        }
+
+        if let Some(cellKind) = self.tokenizer.takeCellKindMarker() {
+            self.info.mutables.push((nodeId, cellKind));
+        }
+
+        let mut childCount = 0;
+        while let Some((_, '(')) = self.tokenizer.peek() {
+            self.tokenizer.checkChildCount(childCount)?;
+            self.parseNode(Some(nodeId))?;
+            childCount += 1;
+        }
+        self.tokenizer.expect(')')
+    }
+}
+
+// Reads the brackets and markers of the notation, shared by the parsers of strands and of strand patterns.
+pub(crate) struct BracketTokenizer
+{
+    characters: Vec<(usize, char)>,
+    position: usize,
+    // What the text describes, for errors at its end.
+    subject: &'static str
+}
+
+impl BracketTokenizer
+{
+    pub(crate) fn new(text: &str, subject: &'static str) -> Self
+    {
+        Self{
+            characters: text.char_indices().filter(|(_, character)| !character.is_whitespace()).collect(),
+            position: 0,
+            subject}
+    }
+
+    pub(crate) fn peek(&self) -> Option<(usize, char)>
+    {
+        self.characters.get(self.position).copied()
+    }
+
+    pub(crate) fn advance(&mut self)
+    {
+        self.position += 1;
+    }
+
+    pub(crate) fn expect(&mut self, expected: char) -> Result<()>
+    {
+        match self.peek() {
+            Some((_, character)) if character == expected => {
+                self.advance();
+                Ok(())
+            },
+            Some((column, character)) => bail!("Expected '{}', got '{}' at column {}.", expected, character, column + 1),
+            None => bail!("Expected '{}', got the end of the {}.", expected, self.subject)
+        }
+    }
+
+    pub(crate) fn expectEnd(&self) -> Result<()>
+    {
+        match self.peek() {
+            Some((column, character)) =>
+                bail!("Unexpected character '{}' after the root cell at column {}.", character, column + 1),
+            None => Ok(())
+        }
+    }
+
+    pub(crate) fn takeCellKindMarker(&mut self) -> Option<CellKind>
+    {
+        let cellKind = self.peek().and_then(|(_, character)| parseCellKindMarker(character))?;
+        self.advance();
+        Some(cellKind)
+    }
+
+    // Called before parsing another child of a cell which already has the given number of children.
+    pub(crate) fn checkChildCount(&self, childCount: usize) -> Result<()>
+    {
+        if childCount == MAX_CHILD_COUNT {
+            let column = self.peek().map_or(0, |(column, _)| column);
+            bail!("Cell at column {} has more than {} children.", column + 1, MAX_CHILD_COUNT);
+        }
+        Ok(())
+    }
+}
+
+fn parseCellKindMarker(character: char) -> Option<CellKind>
+{
+    match character.to_ascii_uppercase() {
+        'D' => Some(CellKind::Doubler),
+        'E' => Some(CellKind::Extender),
+        'X' => Some(CellKind::Eraser),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::level_maker::LevelCatalog;
+    use crate::strand::SurfaceMatching;
+
+    #[test]
+    fn levelStrandsSurviveARoundTrip()
+    {
+        let catalog = LevelCatalog::load().unwrap();
+        for sequence in catalog.sequences() {
+            for metadata in &sequence.levels {
+                let level = catalog.makeLevel(sequence.number, metadata.strand).unwrap();
+                for strand in [level.start, level.target] {
+                    let text = formatStrand(&strand);
+                    let parsedStrand = parseStrand(&text).unwrap();
+                    assert!(parsedStrand.matchesSurface(&strand, SurfaceMatching::Ordered), "{}", text);
+                    assert_eq!(formatStrand(&parsedStrand), text);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn markersAndWhitespaceAreAccepted()
+    {
+        let strand = parseStrand(" ( (d) (x (E)) ) ").unwrap();
+        assert_eq!(formatStrand(&strand), "((D)(X(E)))");
+    }
+
+    #[test]
+    fn unbalancedBracketsAreRejected()
+    {
+        assert_eq!(parseError("((())"), "Expected ')', got the end of the strand.");
+        assert_eq!(parseError("(()))"), "Unexpected character ')' after the root cell at column 5.");
+        assert_eq!(parseError(")"), "Expected '(', got ')' at column 1.");
+        assert_eq!(parseError(""), "Expected '(', got the end of the strand.");
+    }
+
+    #[test]
+    fn cellsWithMoreThanTwoChildrenAreRejected()
+    {
+        assert_eq!(parseError("(()()())"), "Cell at column 6 has more than 2 children.");
+    }
+
+    #[test]
+    fn unknownMarkersAreRejected()
+    {
+        assert_eq!(parseError("(Q)"), "Expected ')', got 'Q' at column 2.");
+    }
+
+    #[test]
+    fn strandsWithMoreThanMaxNodeCountCellsAreRejected()
+    {
+        let text = "(".repeat(MAX_NODE_COUNT + 1) + &")".repeat(MAX_NODE_COUNT + 1);
+        assert_eq!(parseError(&text), format!("Strand has more than {} cells.", MAX_NODE_COUNT));
+        let text = "(".repeat(MAX_NODE_COUNT) + &")".repeat(MAX_NODE_COUNT);
+        assert!(parseStrand(&text).is_ok());
+    }
+
+    fn parseError(text: &str) -> String
+    {
+        parseStrand(text).unwrap_err().to_string()
+    }
+}
@@ -0,0 +1,198 @@
+//! Partially specified targets the solver can search for instead of an exact strand.
+//!
+//! A pattern is written in the bracket notation of strands with two additions: the marker `?` after an opening
+//! bracket matches a cell of any kind, and `*` in place of a cell matches any subtree. A cell without a marker
+//! still has to be a normal cell. For example `(?*(D))` is a root of any kind with any subtree on the left and
+//! a doubler without children on the right, while `(*)` is a normal cell with one subtree below it,
+//! and `*` alone matches every strand.
+//! The tree can be followed by clauses separated by `;`: `depth N` limits the depth of the strand to N, the root
+//! being at depth 0, and `leaves` followed by cells like `(D)(E)()` requires exactly these leaves in any order.
+//! `*; depth 2; leaves (D)(D)` therefore matches any strand of depth at most 2 with two doublers as its only leaves.
+
+use crate::strand::{CellKind, NodeId, Strand, SurfaceMatching};
+use crate::strand_notation::BracketTokenizer;
+
+use anyhow::{bail, Context, Result};
+use itertools::Itertools as _;
+
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct StrandPattern
+{
+    root: PatternNode,
+    maxDepthOpt: Option<usize>,
+    // Sorted, so that leaves can be compared in any order.
+    leafKindsOpt: Option<Vec<CellKind>>
+}
+
+impl StrandPattern
+{
+    pub(crate) fn matches(&self, strand: &Strand, matching: SurfaceMatching) -> bool
+    {
+        self.maxDepthOpt.is_none_or(|maxDepth| strand.calculateHeight() <= maxDepth)
+            && self.leafKindsOpt.as_ref().is_none_or(|leafKinds| *leafKinds == collectLeafKinds(strand))
+            && self.root.matches(strand, Strand::root(), matching)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum PatternNode
+{
+    Cell{cellKindOpt: Option<CellKind>, children: Vec<PatternNode>},
+    AnySubtree
+}
+
+impl PatternNode
+{
+    fn matches(&self, strand: &Strand, nodeId: NodeId, matching: SurfaceMatching) -> bool
+    {
+        match self {
+            PatternNode::AnySubtree => true,
+            PatternNode::Cell{cellKindOpt, children} => {
+                let childIds = strand.childIds(nodeId);
+                let matchesChildren = |childIds: &[NodeId]| {
+                    children.iter().zip(childIds).all(|(child, childId)| child.matches(strand, *childId, matching))
+                };
+                cellKindOpt.is_none_or(|cellKind| strand.cellKind(nodeId) == cellKind)
+                    && children.len() == childIds.len()
+                    && match matching {
+                        SurfaceMatching::Ordered => matchesChildren(childIds),
+                        SurfaceMatching::Unordered => {
+                            matchesChildren(childIds) || matchesChildren(&childIds.iter().rev().copied().collect_vec())
+                        }
+                    }
+            }
+        }
+    }
+}
+
+fn collectLeafKinds(strand: &Strand) -> Vec<CellKind>
+{
+    strand.collectNodeIds().into_iter()
+        .filter(|nodeId| strand.childCount(*nodeId) == 0)
+        .map(|nodeId| strand.cellKind(nodeId))
+        .sorted_by_key(|cellKind| cellKind.code())
+        .collect()
+}
+
+pub(crate) fn parseStrandPattern(text: &str) -> Result<StrandPattern>
+{
+    let mut clauses = text.split(';');
+    let root = parsePatternTree(clauses.next().unwrap())?;
+    let mut pattern = StrandPattern{root, maxDepthOpt: None, leafKindsOpt: None};
+    for clause in clauses {
+        match clause.trim().split_once(char::is_whitespace) {
+            Some(("depth", depth)) => {
+                let depth = depth.trim();
+                pattern.maxDepthOpt = Some(depth.parse().with_context(|| format!("Invalid depth: {}", depth))?);
+            },
+            Some(("leaves", leaves)) => {
+                let leafKinds = parseLeaves(leaves)?;
+                pattern.leafKindsOpt = Some(leafKinds.into_iter().sorted_by_key(|cellKind| cellKind.code()).collect());
+            },
+            _ => bail!("Unknown pattern clause: {}", clause.trim())
+        }
+    }
+    Ok(pattern)
+}
+
+fn parsePatternTree(text: &str) -> Result<PatternNode>
+{
+    let mut tokenizer = BracketTokenizer::new(text, "pattern");
+    let root = parsePatternNode(&mut tokenizer)?;
+    tokenizer.expectEnd()?;
+    Ok(root)
+}
+
+fn parseLeaves(text: &str) -> Result<Vec<CellKind>>
+{
+    let mut tokenizer = BracketTokenizer::new(text, "pattern");
+    let mut leafKinds = vec![];
+    while tokenizer.peek().is_some() {
+        match parsePatternNode(&mut tokenizer)? {
+            PatternNode::Cell{cellKindOpt: Some(cellKind), children} if children.is_empty() => leafKinds.push(cellKind),
+            _ => bail!("Leaves must be cells of a given kind without children: {}", text.trim())
+        }
+    }
+    Ok(leafKinds)
+}
+
+fn parsePatternNode(tokenizer: &mut BracketTokenizer) -> Result<PatternNode>
+{
+    if let Some((_, '*')) = tokenizer.peek() {
+        tokenizer.advance();
+        return Ok(PatternNode::AnySubtree);
+    }
+
+    tokenizer.expect('(')?;
+    let cellKindOpt = match tokenizer.peek() {
+        Some((_, '?')) => {
+            tokenizer.advance();
+            None
+        },
+        _ => Some(tokenizer.takeCellKindMarker().unwrap_or(CellKind::Normal))
+    };
+
+    let mut children = vec![];
+    while let Some((_, '(' | '*')) = tokenizer.peek() {
+        tokenizer.checkChildCount(children.len())?;
+        children.push(parsePatternNode(tokenizer)?);
+    }
+    tokenizer.expect(')')?;
+    Ok(PatternNode::Cell{cellKindOpt, children})
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::strand_notation::parseStrand;
+
+    #[test]
+    fn wildcardsMatchAnyKindAndAnySubtree()
+    {
+        assert!(matches("*", "((D)(X(E)))", SurfaceMatching::Ordered));
+        assert!(matches("(?*(D))", "(()(D))", SurfaceMatching::Ordered));
+        assert!(matches("(?*(D))", "(((E)())(D))", SurfaceMatching::Ordered));
+        assert!(matches("((?)*)", "((X)())", SurfaceMatching::Ordered));
+        assert!(!matches("(?*(D))", "((D)())", SurfaceMatching::Ordered));
+        assert!(matches("(?*(D))", "((D)())", SurfaceMatching::Unordered));
+        // Cells without a marker are normal cells, and every cell has exactly the children given.
+        assert!(!matches("(*())", "((D)(E))", SurfaceMatching::Ordered));
+        assert!(!matches("(?*(D))", "(()(D()))", SurfaceMatching::Ordered));
+        assert!(!matches("(?*(D))", "((D))", SurfaceMatching::Ordered));
+    }
+
+    #[test]
+    fn depthAndLeavesClausesLimitTheWholeStrand()
+    {
+        assert!(matches("*; depth 2", "((())())", SurfaceMatching::Ordered));
+        assert!(!matches("*; depth 2", "(((())))", SurfaceMatching::Ordered));
+        assert!(matches("*; leaves (D)(D)", "(((D))(D))", SurfaceMatching::Ordered));
+        assert!(matches("*; leaves (E)()", "((E)(()))", SurfaceMatching::Ordered));
+        assert!(!matches("*; leaves (D)(D)", "((D)())", SurfaceMatching::Ordered));
+        assert!(!matches("*; leaves (D)", "((D)(D))", SurfaceMatching::Ordered));
+        assert!(matches("*; depth 2; leaves (D)(D)", "((D)(D))", SurfaceMatching::Ordered));
+        assert!(!matches("*; depth 2; leaves (D)(D)", "((((D)(D))))", SurfaceMatching::Ordered));
+    }
+
+    #[test]
+    fn invalidPatternsAreRejected()
+    {
+        assert_eq!(parseError("*; width 2"), "Unknown pattern clause: width 2");
+        assert_eq!(parseError("*; depth two"), "Invalid depth: two");
+        assert_eq!(parseError("*; leaves (D(E))"), "Leaves must be cells of a given kind without children: (D(E))");
+        assert_eq!(parseError("*; leaves *"), "Leaves must be cells of a given kind without children: *");
+        assert_eq!(parseError("(?"), "Expected ')', got the end of the pattern.");
+    }
+
+    fn matches(pattern: &str, strand: &str, matching: SurfaceMatching) -> bool
+    {
+        parseStrandPattern(pattern).unwrap().matches(&parseStrand(strand).unwrap(), matching)
+    }
+
+    fn parseError(text: &str) -> String
+    {
+        parseStrandPattern(text).unwrap_err().to_string()
+    }
+}